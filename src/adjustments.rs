@@ -0,0 +1,134 @@
+use ckb_types::packed;
+
+use std::collections::HashMap;
+
+use crate::consts::ONE_CKB;
+use crate::network_check::NetworkCheck;
+use crate::previous_rounds::ROUND_NETWORK;
+use crate::{display_lock, sighash_lock_script, AddressParser};
+
+#[derive(Clone, Copy, Debug)]
+pub enum AdjustmentKind {
+    // Signed change in shannons
+    Delta(i64),
+    // New total in shannons
+    Override(u64),
+}
+
+pub struct Adjustment {
+    pub line: u64,
+    pub address: String,
+    pub lock_script: packed::Script,
+    pub kind: AdjustmentKind,
+    pub reason: String,
+    pub approver: String,
+}
+
+// Load adjustments from a CSV file with header
+// `address,kind,amount,reason,approver`, where `kind` is `delta` or
+// `override` and `amount` is in CKB (signed for `delta`). Addresses are the
+// testnet addresses of the round rewards.
+pub fn load_adjustments(
+    path: &str,
+    network_check: &mut NetworkCheck,
+) -> Result<Vec<Adjustment>, String> {
+    let mut rdr = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+    let mut adjustments = Vec::new();
+    for record in rdr.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|position| position.line()).unwrap_or_default();
+        let field = |index: usize, name: &str| -> Result<String, String> {
+            match record.get(index).map(str::trim) {
+                Some(value) if !value.is_empty() => Ok(value.to_string()),
+                _ => Err(format!("{}:{} missing {}", path, line, name)),
+            }
+        };
+        let address = field(0, "address")?;
+        let (network, hash) = AddressParser
+            .parse_network(&address)
+            .map_err(|err| format!("{}:{} invalid address {}: {}", path, line, address, err))?;
+        network_check.expect(&format!("{}:{}", path, line), &address, ROUND_NETWORK, network);
+        let amount = field(2, "amount")?;
+        let kind = match field(1, "kind")?.as_str() {
+            "delta" => amount
+                .trim_start_matches('+')
+                .parse::<i64>()
+                .map(|value| AdjustmentKind::Delta(value * ONE_CKB as i64)),
+            "override" => amount
+                .parse::<u64>()
+                .map(|value| AdjustmentKind::Override(value * ONE_CKB)),
+            kind => {
+                return Err(format!("{}:{} invalid kind: {}", path, line, kind));
+            }
+        }
+        .map_err(|err| format!("{}:{} invalid amount {}: {}", path, line, amount, err))?;
+        adjustments.push(Adjustment {
+            line,
+            address,
+            lock_script: sighash_lock_script(hash),
+            kind,
+            reason: field(3, "reason")?,
+            approver: field(4, "approver")?,
+        });
+    }
+    Ok(adjustments)
+}
+
+pub struct AppliedAdjustment<'a> {
+    pub adjustment: &'a Adjustment,
+    pub before: u64,
+    pub after: u64,
+}
+
+// Applied in file order, a balance never goes negative
+pub fn apply_adjustments<'a>(
+    adjustments: &'a [Adjustment],
+    rewards: &mut HashMap<packed::Script, u64>,
+) -> Result<Vec<AppliedAdjustment<'a>>, String> {
+    let mut applied = Vec::new();
+    for adjustment in adjustments {
+        let before = rewards.get(&adjustment.lock_script).cloned().unwrap_or_default();
+        let after = match adjustment.kind {
+            AdjustmentKind::Delta(delta) => {
+                let after = before as i128 + i128::from(delta);
+                if after < 0 {
+                    return Err(format!(
+                        "adjustment at line {} makes {} negative: {} {:+}",
+                        adjustment.line, adjustment.address, before, delta
+                    ));
+                }
+                after as u64
+            }
+            AdjustmentKind::Override(value) => value,
+        };
+        if after == 0 {
+            rewards.remove(&adjustment.lock_script);
+        } else {
+            rewards.insert(adjustment.lock_script.clone(), after);
+        }
+        applied.push(AppliedAdjustment {
+            adjustment,
+            before,
+            after,
+        });
+    }
+    Ok(applied)
+}
+
+pub fn print_adjustments_report(applied: &[AppliedAdjustment]) {
+    println!("==== Manual adjustments: {}", applied.len());
+    for item in applied {
+        let adjustment = item.adjustment;
+        println!(
+            "  > line {}: {} ({}), {:?}, before: {}, after: {}, reason: {}, approver: {}",
+            adjustment.line,
+            adjustment.address,
+            display_lock(&adjustment.lock_script),
+            adjustment.kind,
+            item.before,
+            item.after,
+            adjustment.reason,
+            adjustment.approver
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::consts::ONE_CKB;
+
+// How a reward pool is split by weight
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Apportionment {
+    // Floor to whole CKB, the residue is not distributed
+    Floor,
+    // Floor to whole CKB, the residue goes to the largest remainders (1 CKB each)
+    LargestRemainder,
+    // Round half to even to whole CKB, then settle the difference by remainders
+    BankersCkb,
+    // Round half to even to shannons, then settle the difference by remainders
+    BankersShannon,
+}
+
+impl Apportionment {
+    pub fn unit(self) -> u64 {
+        match self {
+            Apportionment::BankersShannon => 1,
+            _ => ONE_CKB,
+        }
+    }
+
+    pub fn is_exact(self) -> bool {
+        self != Apportionment::Floor
+    }
+}
+
+impl FromStr for Apportionment {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Apportionment, String> {
+        match input {
+            "floor" => Ok(Apportionment::Floor),
+            "largest-remainder" => Ok(Apportionment::LargestRemainder),
+            "bankers-ckb" => Ok(Apportionment::BankersCkb),
+            "bankers-shannon" => Ok(Apportionment::BankersShannon),
+            _ => Err(format!("Invalid apportionment method: {}", input)),
+        }
+    }
+}
+
+impl fmt::Display for Apportionment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Apportionment::Floor => "floor",
+            Apportionment::LargestRemainder => "largest-remainder",
+            Apportionment::BankersCkb => "bankers-ckb",
+            Apportionment::BankersShannon => "bankers-shannon",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct Share<K> {
+    pub key: K,
+    pub weight: u64,
+    // The exact share in shannons (floored)
+    pub exact: u64,
+    pub amount: u64,
+}
+
+impl<K> Share<K> {
+    // Rounding delta against the exact share, in shannons
+    pub fn delta(&self) -> i64 {
+        self.amount as i64 - self.exact as i64
+    }
+}
+
+// Split `pool * sum(weights) / total_weight` by weight. When all weights are
+// given (`sum(weights) == total_weight`) exact methods sum to `pool` exactly.
+// Ties are settled in input order. Without any weight, e.g. every miner is
+// dropped or excluded, no share is given and the pool stays undistributed.
+pub fn apportion<K: Clone>(
+    weights: &[(K, u64)],
+    total_weight: u64,
+    pool: u64,
+    method: Apportionment,
+) -> Vec<Share<K>> {
+    if total_weight == 0 {
+        return Vec::new();
+    }
+    let unit = u128::from(method.unit());
+    let denominator = u128::from(total_weight) * unit;
+    let mut units = Vec::with_capacity(weights.len());
+    let mut remainders = Vec::with_capacity(weights.len());
+    let mut rounded_up = Vec::with_capacity(weights.len());
+    for (_, weight) in weights {
+        let numerator = u128::from(*weight) * u128::from(pool);
+        let mut value = numerator / denominator;
+        let remainder = numerator % denominator;
+        let round_up = match method {
+            Apportionment::BankersCkb | Apportionment::BankersShannon => {
+                remainder * 2 > denominator || (remainder * 2 == denominator && value % 2 == 1)
+            }
+            _ => false,
+        };
+        if round_up {
+            value += 1;
+        }
+        units.push(value);
+        remainders.push(remainder);
+        rounded_up.push(round_up);
+    }
+
+    if method.is_exact() && !weights.is_empty() {
+        let total: u128 = weights.iter().map(|(_, weight)| u128::from(*weight)).sum();
+        let target = total * u128::from(pool) / denominator;
+        let current: u128 = units.iter().sum();
+        let mut order: Vec<usize> = (0..weights.len()).collect();
+        if current < target {
+            // Give one unit to the largest remainders not rounded up yet
+            order.retain(|index| !rounded_up[*index]);
+            order.sort_by(|a, b| remainders[*b].cmp(&remainders[*a]));
+            for step in 0..(target - current) as usize {
+                units[order[step % order.len()]] += 1;
+            }
+        } else if current > target {
+            // Take one unit back from the smallest remainders rounded up
+            order.retain(|index| rounded_up[*index]);
+            order.sort_by(|a, b| remainders[*a].cmp(&remainders[*b]));
+            for step in 0..(current - target) as usize {
+                units[order[step % order.len()]] -= 1;
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .zip(units.into_iter())
+        .map(|((key, weight), value)| Share {
+            key: key.clone(),
+            weight: *weight,
+            exact: (u128::from(*weight) * u128::from(pool) / u128::from(total_weight)) as u64,
+            amount: (value * unit) as u64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exact_sum() {
+        let weights = vec![(1, 1), (2, 1), (3, 1)];
+        for method in &[
+            Apportionment::LargestRemainder,
+            Apportionment::BankersCkb,
+            Apportionment::BankersShannon,
+        ] {
+            let shares = apportion(&weights, 3, 100 * ONE_CKB, *method);
+            let total: u64 = shares.iter().map(|share| share.amount).sum();
+            assert_eq!(total, 100 * ONE_CKB, "method: {}", method);
+        }
+        let shares = apportion(&weights, 3, 100 * ONE_CKB, Apportionment::Floor);
+        let total: u64 = shares.iter().map(|share| share.amount).sum();
+        assert_eq!(total, 99 * ONE_CKB);
+    }
+
+    #[test]
+    fn test_bankers_half_to_even() {
+        // 2.5 CKB and 1.5 CKB both round to 2 CKB
+        let weights = vec![("a", 5), ("b", 3)];
+        let shares = apportion(&weights, 8, 4 * ONE_CKB, Apportionment::BankersCkb);
+        assert_eq!(shares[0].amount, 2 * ONE_CKB);
+        assert_eq!(shares[1].amount, 2 * ONE_CKB);
+    }
+
+    #[test]
+    fn test_zero_total_weight() {
+        let weights = vec![("a", 0), ("b", 0)];
+        let shares = apportion(&weights, 0, 4 * ONE_CKB, Apportionment::BankersCkb);
+        assert!(shares.is_empty());
+    }
+}
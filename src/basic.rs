@@ -4,13 +4,16 @@ use std::str::FromStr;
 use bech32::{convert_bits, Bech32, ToBase32};
 use ckb_hash::blake2b_256;
 use ckb_types::{
+    bytes::Bytes,
     core::ScriptHashType,
-    packed::{Byte32, Script},
+    packed::{Byte, Byte32, Script},
     prelude::*,
     H160, H256,
 };
 use serde_derive::{Deserialize, Serialize};
 
+use crate::consts::SECP_TYPE_SCRIPT_HASH;
+
 pub use old_addr::{Address as OldAddress, AddressFormat as OldAddressFormat};
 
 const PREFIX_MAINNET: &str = "ckb";
@@ -77,6 +80,9 @@ impl fmt::Display for NetworkType {
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum AddressType {
+    // Full format: code_hash, hash_type and args, encoded with bech32m
+    Full = 0x00,
+    // Short format with a code hash index
     Default = 0x01,
 }
 
@@ -87,30 +93,87 @@ pub enum CodeHashIndex {
     Default = 0x00,
 }
 
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum AddressPayload {
+    Short {
+        index: CodeHashIndex,
+        hash: H160,
+    },
+    Full {
+        code_hash: H256,
+        hash_type: u8,
+        args: Vec<u8>,
+    },
+}
+
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Address {
-    ty: AddressType,
-    index: CodeHashIndex,
-    hash: H160,
+    payload: AddressPayload,
 }
 
 impl Address {
     pub fn new_default(hash: H160) -> Address {
-        let ty = AddressType::Default;
         let index = CodeHashIndex::Default;
-        Address { ty, index, hash }
+        Address {
+            payload: AddressPayload::Short { index, hash },
+        }
+    }
+
+    // Keeps the full format as written, even for the default sighash lock
+    pub fn new_full(script: &Script) -> Address {
+        Address {
+            payload: AddressPayload::Full {
+                code_hash: script.code_hash().unpack(),
+                hash_type: script.hash_type().as_slice()[0],
+                args: script.args().raw_data().to_vec(),
+            },
+        }
     }
 
-    pub fn hash(&self) -> &H160 {
-        &self.hash
+    // Lock args of the default sighash lock in either format, `None` for other locks
+    pub fn hash(&self) -> Option<H160> {
+        match self.payload {
+            AddressPayload::Short { ref hash, .. } => Some(hash.clone()),
+            AddressPayload::Full {
+                ref code_hash,
+                hash_type,
+                ref args,
+            } => {
+                if *code_hash == SECP_TYPE_SCRIPT_HASH
+                    && Byte::new(hash_type) == ScriptHashType::Type.into()
+                    && args.len() == 20
+                {
+                    H160::from_slice(args).ok()
+                } else {
+                    None
+                }
+            }
+        }
     }
 
+    // Full addresses carry their own code hash, `type_hash` is only used by short addresses
     pub fn lock_script(&self, type_hash: Byte32) -> Script {
-        Script::new_builder()
-            .args(self.hash.as_bytes().pack())
-            .code_hash(type_hash)
-            .hash_type(ScriptHashType::Type.into())
-            .build()
+        match self.payload {
+            AddressPayload::Short { ref hash, .. } => Script::new_builder()
+                .args(hash.as_bytes().pack())
+                .code_hash(type_hash)
+                .hash_type(ScriptHashType::Type.into())
+                .build(),
+            AddressPayload::Full {
+                ref code_hash,
+                hash_type,
+                ref args,
+            } => Script::new_builder()
+                .args(Bytes::from(args.clone()).pack())
+                .code_hash(code_hash.pack())
+                .hash_type(Byte::new(hash_type))
+                .build(),
+        }
+    }
+
+    // The exact lock script of the address
+    pub fn to_script(&self) -> Script {
+        self.lock_script(SECP_TYPE_SCRIPT_HASH.pack())
     }
 
     pub fn from_pubkey(pubkey: &secp256k1::PublicKey) -> Result<Address, String> {
@@ -125,11 +188,29 @@ impl Address {
         Ok(Self::new_default(hash))
     }
 
+    // Full format in bech32m or short format in bech32, errors of both
+    // decoders are reported when neither accepts the input
     pub fn from_input(input: &str) -> Result<(NetworkType, Address), String> {
+        Self::from_full_input(input).or_else(|full_err| {
+            Self::from_short_input(input).map_err(|short_err| {
+                format!("full format: {}, short format: {}", full_err, short_err)
+            })
+        })
+    }
+
+    fn from_full_input(input: &str) -> Result<(NetworkType, Address), String> {
+        let (hrp, data) = bech32m::decode(input)?;
+        let network =
+            NetworkType::from_prefix(&hrp).ok_or_else(|| format!("Invalid hrp: {}", hrp))?;
+        let data = convert_bits(&data, 5, 8, false).map_err(|err| err.to_string())?;
+        Self::from_full_payload(&data).map(|address| (network, address))
+    }
+
+    fn from_short_input(input: &str) -> Result<(NetworkType, Address), String> {
         let value = Bech32::from_str(input).map_err(|err| err.to_string())?;
         let network = NetworkType::from_prefix(value.hrp())
             .ok_or_else(|| format!("Invalid hrp: {}", value.hrp()))?;
-        let data = convert_bits(value.data(), 5, 8, false).unwrap();
+        let data = convert_bits(value.data(), 5, 8, false).map_err(|err| err.to_string())?;
         if data.len() != 22 {
             return Err(format!("Invalid input data length {}", data.len()));
         }
@@ -143,15 +224,128 @@ impl Address {
         Ok((network, Self::new_default(hash)))
     }
 
+    fn from_full_payload(data: &[u8]) -> Result<Address, String> {
+        if data.len() < 34 {
+            return Err(format!("Invalid full address data length {}", data.len()));
+        }
+        if data[0] != AddressType::Full as u8 {
+            return Err(format!("Invalid bech32m address type: {:?}", data[0]));
+        }
+        // data or type
+        if data[33] > 1 {
+            return Err(format!("Invalid hash type: {:?}", data[33]));
+        }
+        let code_hash = H256::from_slice(&data[1..33]).map_err(|err| err.to_string())?;
+        let script = Script::new_builder()
+            .code_hash(code_hash.pack())
+            .hash_type(Byte::new(data[33]))
+            .args(Bytes::from(data[34..].to_vec()).pack())
+            .build();
+        Ok(Self::new_full(&script))
+    }
+
     pub fn to_string(&self, network: NetworkType) -> String {
         let hrp = network.to_prefix();
-        let mut data = [0; 22];
-        data[0] = self.ty as u8;
-        data[1] = self.index as u8;
-        data[2..22].copy_from_slice(self.hash.as_bytes());
-        let value = Bech32::new(hrp.to_string(), data.to_base32())
-            .unwrap_or_else(|_| panic!("Encode address failed: hash={:?}", self.hash));
-        format!("{}", value)
+        match self.payload {
+            AddressPayload::Short { index, ref hash } => {
+                let mut data = [0; 22];
+                data[0] = AddressType::Default as u8;
+                data[1] = index as u8;
+                data[2..22].copy_from_slice(hash.as_bytes());
+                let value = Bech32::new(hrp.to_string(), data.to_base32())
+                    .unwrap_or_else(|_| panic!("Encode address failed: hash={:?}", hash));
+                format!("{}", value)
+            }
+            AddressPayload::Full {
+                ref code_hash,
+                hash_type,
+                ref args,
+            } => {
+                let mut data = vec![AddressType::Full as u8];
+                data.extend_from_slice(code_hash.as_bytes());
+                data.push(hash_type);
+                data.extend_from_slice(args);
+                bech32m::encode(hrp, &convert_bits(&data, 8, 5, true).unwrap())
+            }
+        }
+    }
+}
+
+// bech32m (BIP-350) checksum, `bech32 0.6` only knows the original bech32
+// constant and limits the length to 90 characters, which full addresses exceed.
+mod bech32m {
+    const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+    const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+    fn polymod(values: &[u8]) -> u32 {
+        const GENERATOR: [u32; 5] = [
+            0x3b6a_57b2,
+            0x2650_8e6d,
+            0x1ea1_19fa,
+            0x3d42_33dd,
+            0x2a14_62b3,
+        ];
+        let mut checksum: u32 = 1;
+        for value in values {
+            let top = checksum >> 25;
+            checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(*value);
+            for (index, generator) in GENERATOR.iter().enumerate() {
+                if (top >> index) & 1 == 1 {
+                    checksum ^= generator;
+                }
+            }
+        }
+        checksum
+    }
+
+    fn hrp_expand(hrp: &str) -> Vec<u8> {
+        let mut values: Vec<u8> = hrp.bytes().map(|byte| byte >> 5).collect();
+        values.push(0);
+        values.extend(hrp.bytes().map(|byte| byte & 31));
+        values
+    }
+
+    // `data` is in 5 bits groups
+    pub fn encode(hrp: &str, data: &[u8]) -> String {
+        let mut values = hrp_expand(hrp);
+        values.extend_from_slice(data);
+        values.extend_from_slice(&[0; 6]);
+        let checksum = polymod(&values) ^ BECH32M_CONST;
+        let checksum_values = (0..6).map(|index| ((checksum >> (5 * (5 - index))) & 31) as u8);
+        let mut result = format!("{}1", hrp);
+        for value in data.iter().cloned().chain(checksum_values) {
+            result.push(CHARSET[value as usize] as char);
+        }
+        result
+    }
+
+    // Returns hrp and data in 5 bits groups without the checksum
+    pub fn decode(input: &str) -> Result<(String, Vec<u8>), String> {
+        if input.to_lowercase() != input && input.to_uppercase() != input {
+            return Err("Mixed case".to_string());
+        }
+        let input = input.to_lowercase();
+        let separator = input.rfind('1').ok_or_else(|| "Missing separator".to_string())?;
+        let (hrp, data) = (&input[..separator], &input[separator + 1..]);
+        if hrp.is_empty() || data.len() < 6 {
+            return Err("Invalid length".to_string());
+        }
+        let values = data
+            .chars()
+            .map(|c| {
+                CHARSET
+                    .iter()
+                    .position(|item| *item as char == c)
+                    .map(|position| position as u8)
+                    .ok_or_else(|| format!("Invalid character: {}", c))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        let mut checked = hrp_expand(hrp);
+        checked.extend_from_slice(&values);
+        if polymod(&checked) != BECH32M_CONST {
+            return Err("Invalid bech32m checksum".to_string());
+        }
+        Ok((hrp.to_string(), values[..values.len() - 6].to_vec()))
     }
 }
 
@@ -288,4 +482,56 @@ mod test {
             "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p"
         );
     }
+
+    #[test]
+    fn test_full_address() {
+        // Sample from nervosnetwork RFC#21, the default sighash lock in full format
+        let (network, address) = Address::from_input(
+            "ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqdnnw7qkdnnclfkg59uzn8umtfd2kwxceqxwquc4",
+        )
+        .unwrap();
+        assert_eq!(network, NetworkType::MainNet);
+        assert_eq!(
+            address.hash(),
+            Some(h160!("0xb39bbc0b3673c7d36450bc14cfcdad2d559c6c64"))
+        );
+        // Written back in full format
+        assert_eq!(
+            address.to_string(NetworkType::MainNet),
+            "ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqdnnw7qkdnnclfkg59uzn8umtfd2kwxceqxwquc4"
+        );
+
+        let script = Script::new_builder()
+            .code_hash(H256::from_slice(&[1; 32]).unwrap().pack())
+            .hash_type(ScriptHashType::Data.into())
+            .args(Bytes::from(vec![1, 2]).pack())
+            .build();
+        let address = Address::new_full(&script);
+        let encoded = address.to_string(NetworkType::TestNet);
+        assert_eq!(
+            encoded,
+            "ckt1qqqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqqpqg7udadt"
+        );
+        let (network, decoded) = Address::from_input(&encoded).unwrap();
+        assert_eq!(network, NetworkType::TestNet);
+        assert_eq!(decoded.hash(), None);
+        assert_eq!(decoded.to_script(), script);
+    }
+
+    #[test]
+    fn test_invalid_full_address() {
+        let mut data = vec![AddressType::Full as u8];
+        data.extend_from_slice(&[1; 32]);
+        data.push(0);
+        data.extend_from_slice(&[1, 2]);
+        let mut values = convert_bits(&data, 8, 5, true).unwrap();
+        // Valid checksum over non-zero padding bits
+        *values.last_mut().unwrap() |= 1;
+        let err = Address::from_input(&bech32m::encode("ckt", &values)).unwrap_err();
+        assert!(err.contains("full format") && err.contains("short format"), "{}", err);
+
+        let values = convert_bits(&data, 8, 5, true).unwrap();
+        let err = Address::from_input(&bech32m::encode("xyz", &values)).unwrap_err();
+        assert!(err.contains("Invalid hrp: xyz") && err.contains("short format"), "{}", err);
+    }
 }
@@ -0,0 +1,116 @@
+use ckb_chain_spec::ChainSpec;
+use ckb_types::{bytes::Bytes, core::ScriptHashType, packed, prelude::*, H256};
+use serde_derive::Deserialize;
+
+use crate::checks::GenesisScripts;
+use crate::consts::INIT_BURN;
+use crate::display_lock;
+
+// `data/burn-cells.toml`
+#[derive(Deserialize)]
+struct BurnCellsFile {
+    burn_cells: Vec<BurnCellEntry>,
+}
+
+#[derive(Deserialize)]
+struct BurnCellEntry {
+    args: String,
+    // Share of `INIT_BURN` in 0.1%
+    permille: u64,
+}
+
+pub fn burn_lock(args: &str) -> packed::Script {
+    let args = crate::hex_decode(args.trim_start_matches("0x")).unwrap();
+    packed::Script::new_builder()
+        .code_hash(H256::default().pack())
+        .hash_type(ScriptHashType::Data.into())
+        .args(Bytes::from(args).pack())
+        .build()
+}
+
+pub fn burn_cells() -> Vec<(packed::Script, u64)> {
+    let file: BurnCellsFile = toml::from_str(crate::data::BURN_CELLS).unwrap();
+    file.burn_cells
+        .iter()
+        .map(|entry| (burn_lock(&entry.args), INIT_BURN / 1000 * entry.permille))
+        .collect()
+}
+
+// Every zero code hash issued cell must be declared in `burn-cells.toml`, and no
+// genesis cell has the zero hash as data hash or type hash, so the cells can
+// never be unlocked. Returns the proof lines for the report.
+pub fn check_burn_cells(spec: &ChainSpec, scripts: &GenesisScripts) -> Result<Vec<String>, String> {
+    let declared = burn_cells();
+    let total: u64 = declared.iter().map(|(_, capacity)| capacity).sum();
+    if total != INIT_BURN {
+        return Err(format!("burn-cells.toml total {} != INIT_BURN {}", total, INIT_BURN));
+    }
+    let mut proofs = Vec::new();
+    let mut found = vec![0; declared.len()];
+    for (index, cell) in spec.genesis.issued_cells.iter().enumerate() {
+        let lock: packed::Script = cell.lock.clone().into();
+        let code_hash: H256 = lock.code_hash().unpack();
+        if code_hash != H256::default() {
+            continue;
+        }
+        let capacity = cell.capacity.as_u64();
+        let position = declared
+            .iter()
+            .position(|(burn_lock, burn_capacity)| burn_lock == &lock && *burn_capacity == capacity)
+            .ok_or_else(|| {
+                format!("undeclared burn cell #{}: {}, {}", index, capacity, display_lock(&lock))
+            })?;
+        found[position] += 1;
+        if scripts.resolves(&code_hash) {
+            return Err(format!(
+                "burn cell #{} code hash {:#x} resolves to a genesis cell",
+                index, code_hash
+            ));
+        }
+        proofs.push(format!(
+            "burn cell #{}: capacity: {}, lock: {}, code hash {:#x} is neither a data hash nor a type hash of any genesis cell, provably burned",
+            index,
+            capacity,
+            display_lock(&lock),
+            code_hash
+        ));
+    }
+    for ((lock, capacity), count) in declared.iter().zip(found) {
+        if count != 1 {
+            return Err(format!(
+                "burn cell {} with capacity {} issued {} times",
+                display_lock(lock),
+                capacity,
+                count
+            ));
+        }
+    }
+    Ok(proofs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_chain_spec::IssuedCell;
+    use ckb_types::{core::Capacity, H160};
+
+    #[test]
+    fn test_check_burn_cells() {
+        let mut spec: ChainSpec = toml::from_str(crate::data::CHAIN_CHAIN_SPEC).unwrap();
+        for (lock_script, capacity) in burn_cells() {
+            spec.genesis.issued_cells.push(IssuedCell {
+                capacity: Capacity::shannons(capacity),
+                lock: lock_script.into(),
+            });
+        }
+        // Issued cells have empty data, whose data hash is the zero hash
+        spec.genesis.issued_cells.push(IssuedCell {
+            capacity: Capacity::shannons(1000 * crate::consts::ONE_CKB),
+            lock: crate::sighash_lock_script(H160::default()).into(),
+        });
+        let consensus = spec.build_consensus().unwrap();
+        let scripts = GenesisScripts::from_consensus(&spec, &consensus).unwrap();
+        assert!(!scripts.resolves(&H256::default()));
+        assert_eq!(check_burn_cells(&spec, &scripts).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,252 @@
+use ckb_types::packed;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::consts::INIT_TOTAL;
+use crate::genesis_final::{GenesisRecord, NETWORK};
+use crate::basic::Address;
+use crate::network_check::NetworkCheck;
+
+// Allocation categories published in the base spec
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Category {
+    PublicSale,
+    Ecosystem,
+    Team,
+    PrivateSale,
+    StrategicPartners,
+    Foundation,
+    Testnet,
+}
+
+pub const CATEGORIES: &[Category] = &[
+    Category::PublicSale,
+    Category::Ecosystem,
+    Category::Team,
+    Category::PrivateSale,
+    Category::StrategicPartners,
+    Category::Foundation,
+    Category::Testnet,
+];
+
+impl Category {
+    // Published share of `INIT_TOTAL` in 0.1%
+    pub fn permille(self) -> u64 {
+        match self {
+            Category::PublicSale => 215,
+            Category::Ecosystem => 170,
+            Category::Team => 150,
+            Category::PrivateSale => 140,
+            Category::StrategicPartners => 50,
+            Category::Foundation => 20,
+            Category::Testnet => 5,
+        }
+    }
+
+    pub fn expected(self) -> u64 {
+        INIT_TOTAL / 1000 * self.permille()
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Category, String> {
+        match input {
+            "public-sale" => Ok(Category::PublicSale),
+            "ecosystem" => Ok(Category::Ecosystem),
+            "team" => Ok(Category::Team),
+            "private-sale" => Ok(Category::PrivateSale),
+            "strategic-partners" => Ok(Category::StrategicPartners),
+            "foundation" => Ok(Category::Foundation),
+            "testnet" => Ok(Category::Testnet),
+            _ => Err(format!("Invalid category: {}", input)),
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Category::PublicSale => "public-sale",
+            Category::Ecosystem => "ecosystem",
+            Category::Team => "team",
+            Category::PrivateSale => "private-sale",
+            Category::StrategicPartners => "strategic-partners",
+            Category::Foundation => "foundation",
+            Category::Testnet => "testnet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Category of `genesis_final.csv` rows by address, loaded from a CSV file
+// with header `address,category`. The release file is signed, so rows are
+// tagged here unless they carry a `category` column themselves.
+#[derive(Default)]
+pub struct CategoryTags {
+    tags: HashMap<packed::Script, (Category, u64)>,
+}
+
+impl CategoryTags {
+    pub fn load(path: &str, network_check: &mut NetworkCheck) -> Result<CategoryTags, String> {
+        let mut rdr = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+        let mut tags = HashMap::new();
+        for record in rdr.records() {
+            let record = record.map_err(|err| err.to_string())?;
+            let line = record.position().map(|position| position.line()).unwrap_or_default();
+            let address = record.get(0).unwrap_or_default().trim();
+            let (network, parsed) = Address::from_input(address)
+                .map_err(|err| format!("{}:{} invalid address {}: {}", path, line, address, err))?;
+            network_check.expect(&format!("{}:{}", path, line), address, NETWORK, network);
+            let lock_script = parsed.to_script();
+            let category = record
+                .get(1)
+                .unwrap_or_default()
+                .trim()
+                .parse::<Category>()
+                .map_err(|err| format!("{}:{} {}", path, line, err))?;
+            if let Some((_, previous_line)) = tags.insert(lock_script, (category, line)) {
+                return Err(format!(
+                    "{}:{} duplicated address {}, first seen at line {}",
+                    path, line, address, previous_line
+                ));
+            }
+        }
+        Ok(CategoryTags { tags })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    // `None` when the row is not tagged
+    pub fn category_of(&self, record: &GenesisRecord) -> Result<Option<Category>, String> {
+        let tagged = self.tags.get(&record.address.to_script()).map(|(category, _)| *category);
+        let column = match record.category {
+            Some(ref value) => Some(
+                value
+                    .parse::<Category>()
+                    .map_err(|err| format!("row {}: {}", record.row, err))?,
+            ),
+            None => None,
+        };
+        match (column, tagged) {
+            (Some(column), Some(tagged)) if column != tagged => Err(format!(
+                "row {}: category column {} conflicts with tag {}",
+                record.row, column, tagged
+            )),
+            (column, tagged) => Ok(column.or(tagged)),
+        }
+    }
+}
+
+// Totals and cell counts keyed by category (`None` for untagged) and unlock date
+#[derive(Default)]
+pub struct CategorySummary {
+    cells: BTreeMap<(Option<Category>, Option<String>), (u64, usize)>,
+}
+
+impl CategorySummary {
+    pub fn add(&mut self, category: Option<Category>, lock_until: Option<String>, capacity: u64) {
+        let entry = self.cells.entry((category, lock_until)).or_default();
+        entry.0 += capacity;
+        entry.1 += 1;
+    }
+
+    pub fn total(&self, category: Option<Category>) -> (u64, usize) {
+        self.cells
+            .iter()
+            .filter(|((key, _), _)| *key == category)
+            .fold((0, 0), |(total, count), (_, (capacity, cells))| {
+                (total + capacity, count + cells)
+            })
+    }
+
+    // Returns the number of categories not matching their published share.
+    // Without any tag (no tags file nor category column) every
+    // `genesis_final.csv` row is untagged, so only unlock dates are reported.
+    pub fn print_report(&self, tagged: bool) -> usize {
+        let issues = if tagged {
+            self.print_categories()
+        } else {
+            println!("==== Genesis allocation categories: skipped, no category tags given");
+            0
+        };
+
+        let mut dates: BTreeMap<Option<&String>, (u64, usize)> = BTreeMap::new();
+        for ((_, lock_until), (capacity, cells)) in &self.cells {
+            let entry = dates.entry(lock_until.as_ref()).or_default();
+            entry.0 += capacity;
+            entry.1 += cells;
+        }
+        println!("==== Genesis allocation unlock dates");
+        for (lock_until, (capacity, cells)) in dates {
+            println!(
+                "  > unlock: {}, total: {}, cells: {}",
+                lock_until.map(String::as_str).unwrap_or("-"),
+                capacity,
+                cells
+            );
+        }
+        issues
+    }
+
+    fn print_categories(&self) -> usize {
+        println!("==== Genesis allocation categories");
+        let mut issues = 0;
+        for category in CATEGORIES {
+            let (total, count) = self.total(Some(*category));
+            let expected = category.expected();
+            let status = if total == expected {
+                "OK".to_string()
+            } else {
+                issues += 1;
+                format!("MISMATCH {:+}", total as i128 - expected as i128)
+            };
+            println!(
+                "  > {}: {}.{}%, expected: {}, total: {}, cells: {}, {}",
+                category,
+                category.permille() / 10,
+                category.permille() % 10,
+                expected,
+                total,
+                count,
+                status
+            );
+            for ((_, lock_until), (capacity, cells)) in self
+                .cells
+                .iter()
+                .filter(|((key, _), _)| *key == Some(*category))
+            {
+                println!(
+                    "    unlock: {}, total: {}, cells: {}",
+                    lock_until.as_ref().map(String::as_str).unwrap_or("-"),
+                    capacity,
+                    cells
+                );
+            }
+        }
+        let (untagged, untagged_count) = self.total(None);
+        if untagged_count > 0 {
+            issues += 1;
+            println!("  > UNTAGGED total: {}, cells: {}", untagged, untagged_count);
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_published_shares() {
+        let total: u64 = CATEGORIES.iter().map(|category| category.expected()).sum();
+        // The rest 25% is burned
+        assert_eq!(total + crate::consts::INIT_BURN, INIT_TOTAL);
+        assert_eq!(Category::Testnet.expected(), crate::consts::INIT_TESTNET);
+        assert_eq!(Category::Foundation.expected(), crate::consts::INIT_FOUNDATION);
+    }
+}
@@ -0,0 +1,174 @@
+use ckb_chain_spec::{consensus::Consensus, ChainSpec};
+use ckb_types::{
+    core::{Capacity, ScriptHashType},
+    packed,
+    prelude::*,
+    H256,
+};
+
+use std::collections::HashSet;
+use std::fs;
+
+use crate::consts::{MULTISIG_TYPE_SCRIPT_HASH, SECP_TYPE_SCRIPT_HASH};
+use crate::display_lock;
+
+// Reload the written spec file and make sure it builds the same genesis block
+pub fn verify_spec_file(path: &str, expected_hash: &H256) -> Result<(), String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let spec: ChainSpec = toml::from_str(content.as_str()).map_err(|err| err.to_string())?;
+    if spec.genesis.hash.as_ref() != Some(expected_hash) {
+        return Err(format!(
+            "genesis.hash in {} is {:?}, expected: {:#x}",
+            path, spec.genesis.hash, expected_hash
+        ));
+    }
+    // `build_consensus` also checks `genesis.hash` against the genesis block it builds
+    let consensus = spec.build_consensus().map_err(|err| err.to_string())?;
+    let actual_hash: H256 = consensus.genesis_hash().unpack();
+    if &actual_hash != expected_hash {
+        return Err(format!(
+            "genesis hash mismatch after reload {}: actual: {:#x}, expected: {:#x}",
+            path, actual_hash, expected_hash
+        ));
+    }
+    Ok(())
+}
+
+// Output index of system cells in genesis cellbase, output 0 is the genesis message cell
+fn system_cell_output_index(spec: &ChainSpec, name: &str) -> Result<usize, String> {
+    spec.genesis
+        .system_cells
+        .iter()
+        .position(|cell| cell.file.to_string().contains(name))
+        .map(|index| index + 1)
+        .ok_or_else(|| format!("system cell not found: {}", name))
+}
+
+pub struct GenesisScripts {
+    pub sighash_type_hash: H256,
+    pub multisig_type_hash: H256,
+    type_hashes: HashSet<H256>,
+    data_hashes: HashSet<H256>,
+}
+
+impl GenesisScripts {
+    pub fn from_consensus(spec: &ChainSpec, consensus: &Consensus) -> Result<Self, String> {
+        let mut type_hashes = HashSet::new();
+        let mut data_hashes = HashSet::new();
+        for tx in consensus.genesis_block().transactions() {
+            for (output, data) in tx.outputs().into_iter().zip(tx.outputs_data().into_iter()) {
+                if let Some(type_script) = output.type_().to_opt() {
+                    type_hashes.insert(type_script.calc_script_hash().unpack());
+                }
+                // The data hash of empty data is the zero hash, and empty data is no code
+                if !data.raw_data().is_empty() {
+                    data_hashes
+                        .insert(packed::CellOutput::calc_data_hash(&data.raw_data()).unpack());
+                }
+            }
+        }
+
+        let cellbase = &consensus.genesis_block().transactions()[0];
+        let type_hash_of = |name: &str| -> Result<H256, String> {
+            let index = system_cell_output_index(spec, name)?;
+            cellbase
+                .outputs()
+                .get(index)
+                .and_then(|output| output.type_().to_opt())
+                .map(|type_script| type_script.calc_script_hash().unpack())
+                .ok_or_else(|| format!("system cell {} has no type script", name))
+        };
+        Ok(GenesisScripts {
+            sighash_type_hash: type_hash_of("secp256k1_blake160_sighash_all")?,
+            multisig_type_hash: type_hash_of("secp256k1_blake160_multisig_all")?,
+            type_hashes,
+            data_hashes,
+        })
+    }
+
+    // Assertion only: issued locks are always built from the hashes in
+    // `consts.rs`, the derived hashes are never used to build locks. A custom
+    // system cell layout fails here until the consts are updated to match.
+    pub fn check_consts(&self) -> Result<(), String> {
+        if self.sighash_type_hash != SECP_TYPE_SCRIPT_HASH {
+            return Err(format!(
+                "SECP_TYPE_SCRIPT_HASH mismatch: const: {:#x}, genesis: {:#x}, update consts.rs",
+                SECP_TYPE_SCRIPT_HASH, self.sighash_type_hash
+            ));
+        }
+        if self.multisig_type_hash != MULTISIG_TYPE_SCRIPT_HASH {
+            return Err(format!(
+                "MULTISIG_TYPE_SCRIPT_HASH mismatch: const: {:#x}, genesis: {:#x}, update consts.rs",
+                MULTISIG_TYPE_SCRIPT_HASH, self.multisig_type_hash
+            ));
+        }
+        Ok(())
+    }
+
+    // Whether any genesis cell has the code hash as data hash or type hash
+    pub fn resolves(&self, code_hash: &H256) -> bool {
+        self.type_hashes.contains(code_hash) || self.data_hashes.contains(code_hash)
+    }
+
+    pub fn contains(&self, lock: &packed::Script) -> bool {
+        let code_hash: H256 = lock.code_hash().unpack();
+        if lock.hash_type() == ScriptHashType::Type.into() {
+            self.type_hashes.contains(&code_hash)
+        } else {
+            self.data_hashes.contains(&code_hash)
+        }
+    }
+
+    // Every issued cell must be locked by a script that exists in genesis,
+    // except burn cells which are locked by the zero hash on purpose, see
+    // `burn::check_burn_cells`.
+    pub fn check_issued_cells(&self, spec: &ChainSpec) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for (index, cell) in spec.genesis.issued_cells.iter().enumerate() {
+            let lock: packed::Script = cell.lock.clone().into();
+            let code_hash: H256 = lock.code_hash().unpack();
+            if code_hash == H256::default() {
+                log::info!("issued cell #{} is a burn cell: {}", index, cell.capacity);
+                continue;
+            }
+            if !self.contains(&lock) {
+                errors.push(format!("issued cell #{} lock code hash not in genesis: {}", index, lock));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+// Every issued cell must hold at least its own occupied capacity: 8 bytes
+// capacity and the lock script, the cells have no type script nor data.
+pub fn check_occupied_capacities(spec: &ChainSpec) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for (index, cell) in spec.genesis.issued_cells.iter().enumerate() {
+        let lock: packed::Script = cell.lock.clone().into();
+        let output = packed::CellOutput::new_builder()
+            .capacity(cell.capacity.pack())
+            .lock(lock.clone())
+            .build();
+        let occupied = output
+            .occupied_capacity(Capacity::zero())
+            .map_err(|err| format!("issued cell #{} occupied capacity: {}", index, err))?;
+        if cell.capacity < occupied {
+            errors.push(format!(
+                "issued cell #{} capacity {} less than occupied {}: {}",
+                index,
+                cell.capacity,
+                occupied,
+                display_lock(&lock)
+            ));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
@@ -1,4 +1,6 @@
 pub const CHAIN_CHAIN_SPEC: &str = include_str!("base-spec.toml");
+pub const TESTNET_SPEC: &str = include_str!("testnet.toml");
+pub const BURN_CELLS: &str = include_str!("burn-cells.toml");
 
 pub const DATA_ROUND1: &str = include_str!("round1.csv");
 
@@ -0,0 +1,158 @@
+use ckb_types::{packed, H160};
+
+use crate::network_check::NetworkCheck;
+use crate::previous_rounds::ROUND_NETWORK;
+use crate::{display_lock, sighash_lock_script, AddressParser};
+
+// Round names used by `previous_rounds::all_rewards`
+pub const ROUND_NAMES: &[&str] = &["1", "2", "3", "4", "5-stage1", "5-stage2", "last-round"];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExclusionScope {
+    All,
+    Round(String),
+}
+
+impl ExclusionScope {
+    fn covers(&self, round: &str) -> bool {
+        match self {
+            ExclusionScope::All => true,
+            ExclusionScope::Round(name) => name == round,
+        }
+    }
+}
+
+pub struct Exclusion {
+    pub line: u64,
+    pub address: String,
+    pub lock_script: packed::Script,
+    pub scope: ExclusionScope,
+    pub reason: String,
+}
+
+pub struct ExcludedReward {
+    pub round: String,
+    // Capacity of previous rounds, base reward of the last round
+    pub amount: u64,
+    pub line: u64,
+}
+
+// Disqualified miners (cheating, sybil), loaded from a CSV file with header
+// `address,scope,reason`, where `address` is an address of any lock or the
+// sighash lock args in hex and `scope` is `all` or one of `ROUND_NAMES`.
+// Addresses are testnet addresses, rewards are matched by the full lock script.
+#[derive(Default)]
+pub struct Exclusions {
+    entries: Vec<Exclusion>,
+    pub excluded: Vec<ExcludedReward>,
+}
+
+impl Exclusions {
+    pub fn load(path: &str, network_check: &mut NetworkCheck) -> Result<Exclusions, String> {
+        let mut rdr = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+        let mut entries = Vec::new();
+        for record in rdr.records() {
+            let record = record.map_err(|err| err.to_string())?;
+            let line = record.position().map(|position| position.line()).unwrap_or_default();
+            let address = record.get(0).unwrap_or_default().trim().to_string();
+            let lock_script = if address.starts_with("0x") {
+                crate::hex_decode(&address[2..])
+                    .and_then(|bytes| H160::from_slice(&bytes).map_err(|err| err.to_string()))
+                    .map(sighash_lock_script)
+            } else {
+                AddressParser.parse_script(&address).map(|(network, lock_script)| {
+                    let source = format!("{}:{}", path, line);
+                    network_check.expect(&source, &address, ROUND_NETWORK, network);
+                    lock_script
+                })
+            }
+            .map_err(|err| format!("{}:{} invalid address {}: {}", path, line, address, err))?;
+            let scope = match record.get(1).unwrap_or_default().trim() {
+                "all" => ExclusionScope::All,
+                name if ROUND_NAMES.contains(&name) => ExclusionScope::Round(name.to_string()),
+                name => return Err(format!("{}:{} invalid scope: {}", path, line, name)),
+            };
+            let reason = record.get(2).unwrap_or_default().trim().to_string();
+            if reason.is_empty() {
+                return Err(format!("{}:{} missing reason", path, line));
+            }
+            entries.push(Exclusion {
+                line,
+                address,
+                lock_script,
+                scope,
+                reason,
+            });
+        }
+        Ok(Exclusions::new(entries))
+    }
+
+    pub fn new(entries: Vec<Exclusion>) -> Exclusions {
+        Exclusions {
+            entries,
+            excluded: Vec::new(),
+        }
+    }
+
+    fn find(&self, round: &str, lock_script: &packed::Script) -> Option<&Exclusion> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.lock_script == lock_script && entry.scope.covers(round))
+    }
+
+    // Returns true and records it when the lock is excluded from the round
+    pub fn exclude(&mut self, round: &str, lock_script: &packed::Script, amount: u64) -> bool {
+        let line = match self.find(round, lock_script) {
+            Some(entry) => entry.line,
+            None => return false,
+        };
+        log::info!("excluded round{}: {} => {}", round, display_lock(lock_script), amount);
+        self.excluded.push(ExcludedReward {
+            round: round.to_string(),
+            amount,
+            line,
+        });
+        true
+    }
+
+    pub fn filter(
+        &mut self,
+        round: &str,
+        rewards: Vec<(packed::Script, u64)>,
+    ) -> Vec<(packed::Script, u64)> {
+        rewards
+            .into_iter()
+            .filter(|(lock_script, capacity)| !self.exclude(round, lock_script, *capacity))
+            .collect()
+    }
+
+    pub fn print_report(&self) {
+        println!(
+            "==== Excluded miners: {}, excluded rewards: {}",
+            self.entries.len(),
+            self.excluded.len()
+        );
+        for entry in &self.entries {
+            let excluded: Vec<_> = self
+                .excluded
+                .iter()
+                .filter(|item| item.line == entry.line)
+                .collect();
+            println!(
+                "  > line {}: {} ({}), scope: {:?}, reason: {}, total: {}",
+                entry.line,
+                entry.address,
+                display_lock(&entry.lock_script),
+                entry.scope,
+                entry.reason,
+                excluded.iter().map(|item| item.amount).sum::<u64>()
+            );
+            if excluded.is_empty() {
+                println!("    WARN: matches no reward");
+            }
+            for item in excluded {
+                println!("    round{}: {}", item.round, item.amount);
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use ckb_chain_spec::{consensus::Consensus, ChainSpec};
+use ckb_types::prelude::*;
+
+use std::fmt;
+
+use crate::consts::{INIT_FOUNDATION, ONE_CKB};
+
+// Foundation Reserve: 2% - genesis message cell - system cells - dep groups
+pub struct FoundationReserve {
+    pub genesis_cell: u64,
+    pub system_cells: Vec<(String, u64)>,
+    pub dep_groups: Vec<(String, u64)>,
+    pub reserve: u64,
+}
+
+impl FoundationReserve {
+    pub fn from_consensus(spec: &ChainSpec, consensus: &Consensus) -> Result<Self, String> {
+        let transactions = consensus.genesis_block().transactions();
+        let capacity_at = |tx_index: usize, output_index: usize| -> Result<u64, String> {
+            transactions
+                .get(tx_index)
+                .and_then(|tx| tx.outputs().get(output_index))
+                .map(|output| output.capacity().unpack())
+                .ok_or_else(|| format!("genesis output not found: {}/{}", tx_index, output_index))
+        };
+
+        // Cellbase outputs: genesis message cell, system cells, then issued cells
+        let genesis_cell = capacity_at(0, 0)?;
+        let mut system_cells = Vec::new();
+        for (index, cell) in spec.genesis.system_cells.iter().enumerate() {
+            system_cells.push((cell.file.to_string(), capacity_at(0, index + 1)?));
+        }
+        // The second transaction only contains dep group cells
+        let mut dep_groups = Vec::new();
+        for (index, dep_group) in spec.genesis.dep_groups.iter().enumerate() {
+            dep_groups.push((dep_group.name.clone(), capacity_at(1, index)?));
+        }
+
+        let consumed = genesis_cell
+            + system_cells.iter().map(|(_, capacity)| capacity).sum::<u64>()
+            + dep_groups.iter().map(|(_, capacity)| capacity).sum::<u64>();
+        let reserve = INIT_FOUNDATION
+            .checked_sub(consumed)
+            .ok_or_else(|| format!("cells consumed {} more than INIT_FOUNDATION", consumed))?;
+        Ok(FoundationReserve {
+            genesis_cell,
+            system_cells,
+            dep_groups,
+            reserve,
+        })
+    }
+
+    pub fn check(&self, expected: Option<u64>) -> Result<(), String> {
+        match expected {
+            Some(expected) if expected != self.reserve => Err(format!(
+                "FOUNDATION_RESERVE mismatch: const: {}, computed: {}",
+                expected, self.reserve
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for FoundationReserve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{{")?;
+        writeln!(f, "  init_foundation: {}", INIT_FOUNDATION)?;
+        writeln!(f, "  genesis_cell: {}", self.genesis_cell)?;
+        for (name, capacity) in &self.system_cells {
+            writeln!(f, "  > system_cell: {}, capacity: {}", name, capacity)?;
+        }
+        for (name, capacity) in &self.dep_groups {
+            writeln!(f, "  > dep_group: {}, capacity: {}", name, capacity)?;
+        }
+        writeln!(
+            f,
+            "  reserve: {} ({} CKB + {} shannons)",
+            self.reserve,
+            self.reserve / ONE_CKB,
+            self.reserve % ONE_CKB
+        )?;
+        writeln!(f, "}}")
+    }
+}
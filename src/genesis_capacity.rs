@@ -0,0 +1,234 @@
+use ckb_chain_spec::{consensus::Consensus, ChainSpec};
+use ckb_types::{core::Capacity, packed, prelude::*};
+
+use std::fmt;
+
+// Reward of the first block in an epoch, the remainder goes to the first blocks
+fn first_block_reward(epoch_reward: u64, epoch_length: u64) -> u64 {
+    epoch_reward / epoch_length + if epoch_reward % epoch_length > 0 { 1 } else { 0 }
+}
+
+// (primary, secondary) issuance of the genesis block, the block reward of the
+// genesis epoch
+pub fn genesis_issuance(consensus: &Consensus) -> (u64, u64) {
+    let epoch_length = consensus.genesis_epoch_ext().length();
+    (
+        first_block_reward(consensus.initial_primary_epoch_reward().as_u64(), epoch_length),
+        first_block_reward(consensus.secondary_epoch_reward().as_u64(), epoch_length),
+    )
+}
+
+// Fields of the header `dao`, four little-endian u64
+pub struct DaoField {
+    // Total issued capacity
+    pub c: u64,
+    // Accumulated rate
+    pub ar: u64,
+    // Secondary issuance not in the NervosDAO
+    pub s: u64,
+    // Occupied capacity
+    pub u: u64,
+}
+
+impl DaoField {
+    pub fn from_slice(slice: &[u8]) -> Result<DaoField, String> {
+        if slice.len() != 32 {
+            return Err(format!("invalid dao field length: {}", slice.len()));
+        }
+        let field = |index: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&slice[index * 8..(index + 1) * 8]);
+            u64::from_le_bytes(bytes)
+        };
+        Ok(DaoField {
+            c: field(0),
+            ar: field(1),
+            s: field(2),
+            u: field(3),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct CapacityGroup {
+    pub cells: usize,
+    pub capacity: u64,
+    pub occupied: u64,
+}
+
+// Total and occupied capacity of the genesis block outputs by group
+pub struct GenesisCapacity {
+    pub genesis_cell: CapacityGroup,
+    pub system_cells: CapacityGroup,
+    pub dep_groups: CapacityGroup,
+    pub burn_cells: CapacityGroup,
+    pub issued_cells: CapacityGroup,
+    pub dao: DaoField,
+    // (primary, secondary) issuance of the genesis block, counted in `dao.c`
+    pub issuance: (u64, u64),
+    // `dao.u` counts the satoshi gift cell by ratio instead of its occupied capacity
+    pub satoshi_gift_delta: i128,
+}
+
+impl GenesisCapacity {
+    pub fn from_consensus(spec: &ChainSpec, consensus: &Consensus) -> Result<Self, String> {
+        let mut genesis_cell = CapacityGroup::default();
+        let mut system_cells = CapacityGroup::default();
+        let mut dep_groups = CapacityGroup::default();
+        let mut burn_cells = CapacityGroup::default();
+        let mut issued_cells = CapacityGroup::default();
+        let mut satoshi_gift_delta = 0;
+        let system_cells_count = spec.genesis.system_cells.len();
+        let burn_locks: Vec<packed::Script> =
+            crate::burn::burn_cells().into_iter().map(|(lock, _)| lock).collect();
+        // The cell without data locked by the satoshi pubkey hash has
+        // `capacity * ratio` counted as occupied in `dao.u`
+        let satoshi_gift = &spec.genesis.satoshi_gift;
+        let genesis_block = consensus.genesis_block();
+        for (tx_index, tx) in genesis_block.transactions().iter().enumerate() {
+            for (index, (output, data)) in tx
+                .outputs()
+                .into_iter()
+                .zip(tx.outputs_data().into_iter())
+                .enumerate()
+            {
+                let capacity: u64 = output.capacity().unpack();
+                let data_capacity = Capacity::bytes(data.raw_data().len())
+                    .map_err(|err| format!("genesis output {}/{}: {}", tx_index, index, err))?;
+                let occupied = output
+                    .occupied_capacity(data_capacity)
+                    .map_err(|err| format!("genesis output {}/{}: {}", tx_index, index, err))?;
+                if data.raw_data().is_empty()
+                    && output.lock().args().raw_data()
+                        == satoshi_gift.satoshi_pubkey_hash.as_bytes()
+                {
+                    let ratio = &satoshi_gift.satoshi_cell_occupied_ratio;
+                    let counted = u128::from(capacity) * u128::from(ratio.numer())
+                        / u128::from(ratio.denom());
+                    satoshi_gift_delta += counted as i128 - i128::from(occupied.as_u64());
+                }
+                // Cellbase outputs: genesis message cell, system cells, then issued cells
+                let group = match (tx_index, index) {
+                    (0, 0) => &mut genesis_cell,
+                    (0, index) if index <= system_cells_count => &mut system_cells,
+                    (0, _) if burn_locks.contains(&output.lock()) => &mut burn_cells,
+                    (0, _) => &mut issued_cells,
+                    _ => &mut dep_groups,
+                };
+                group.cells += 1;
+                group.capacity += capacity;
+                group.occupied += occupied.as_u64();
+            }
+        }
+        let dao = DaoField::from_slice(genesis_block.header().dao().as_slice())?;
+        Ok(GenesisCapacity {
+            genesis_cell,
+            system_cells,
+            dep_groups,
+            burn_cells,
+            issued_cells,
+            dao,
+            issuance: genesis_issuance(consensus),
+            satoshi_gift_delta,
+        })
+    }
+
+    fn groups(&self) -> Vec<(&str, &CapacityGroup)> {
+        vec![
+            ("genesis_cell", &self.genesis_cell),
+            ("system_cells", &self.system_cells),
+            ("dep_groups", &self.dep_groups),
+            ("burn_cells", &self.burn_cells),
+            ("issued_cells", &self.issued_cells),
+        ]
+    }
+
+    pub fn total_capacity(&self) -> u64 {
+        self.groups().iter().map(|(_, group)| group.capacity).sum()
+    }
+
+    pub fn total_occupied(&self) -> u64 {
+        self.groups().iter().map(|(_, group)| group.occupied).sum()
+    }
+}
+
+impl fmt::Display for GenesisCapacity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{{")?;
+        for (name, group) in self.groups() {
+            writeln!(
+                f,
+                "  > {}: cells: {}, capacity: {}, occupied: {}, free: {}",
+                name,
+                group.cells,
+                group.capacity,
+                group.occupied,
+                group.capacity.saturating_sub(group.occupied)
+            )?;
+        }
+        let total_capacity = self.total_capacity();
+        let total_occupied = self.total_occupied();
+        writeln!(f, "  total_capacity: {}", total_capacity)?;
+        writeln!(f, "  total_occupied: {}", total_occupied)?;
+        writeln!(f, "  total_free: {}", total_capacity.saturating_sub(total_occupied))?;
+        let (primary, secondary) = self.issuance;
+        let c_delta = i128::from(self.dao.c) - i128::from(total_capacity);
+        writeln!(
+            f,
+            "  dao.c: {}, delta to total_capacity: {}, genesis issuance: {} + {}, unexplained: {}",
+            self.dao.c,
+            c_delta,
+            primary,
+            secondary,
+            c_delta - i128::from(primary) - i128::from(secondary)
+        )?;
+        writeln!(f, "  dao.ar: {}", self.dao.ar)?;
+        writeln!(
+            f,
+            "  dao.s: {}, genesis secondary issuance: {}, unexplained: {}",
+            self.dao.s,
+            secondary,
+            i128::from(self.dao.s) - i128::from(secondary)
+        )?;
+        let u_delta = i128::from(self.dao.u) - i128::from(total_occupied);
+        writeln!(
+            f,
+            "  dao.u: {}, delta to total_occupied: {}, satoshi gift: {}, unexplained: {}",
+            self.dao.u,
+            u_delta,
+            self.satoshi_gift_delta,
+            u_delta - self.satoshi_gift_delta
+        )?;
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dao_field() {
+        let mut bytes = Vec::new();
+        for value in &[1u64, 10_000_000_000_000_000, 3, 0x0102_0304_0506_0708] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let dao = DaoField::from_slice(&bytes).unwrap();
+        assert_eq!(dao.c, 1);
+        assert_eq!(dao.ar, 10_000_000_000_000_000);
+        assert_eq!(dao.s, 3);
+        assert_eq!(dao.u, 0x0102_0304_0506_0708);
+        assert!(DaoField::from_slice(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_genesis_issuance() {
+        // Mainnet genesis: epoch length 1743, `dao.c - total_capacity` is 145238488200
+        // and `dao.s` is 35209330473
+        let mut spec: ChainSpec = toml::from_str(crate::data::CHAIN_CHAIN_SPEC).unwrap();
+        spec.params.genesis_epoch_length = 1743;
+        let (primary, secondary) = genesis_issuance(&spec.build_consensus().unwrap());
+        assert_eq!(secondary, 35_209_330_473);
+        assert_eq!(primary + secondary, 145_238_488_200);
+    }
+}
@@ -1,42 +1,437 @@
 use crate::consts::ONE_CKB;
-use crate::basic::Address;
-use ckb_types::{H160, H256, core::ScriptHashType, bytes::Bytes, packed, prelude::*};
+use crate::apportion::{apportion, Apportionment};
+use crate::basic::{Address, NetworkType};
+use crate::categories::{CategorySummary, CategoryTags};
+use crate::network_check::NetworkCheck;
+use chrono::prelude::*;
+use ckb_types::{core::ScriptHashType, bytes::Bytes, packed, prelude::*};
 
-use std::fs;
+// Recipients of the release file are mainnet addresses
+pub const NETWORK: NetworkType = NetworkType::MainNet;
 
-pub fn read_all_records(last_epoch: u64) -> Vec<(packed::Script, u64)> {
-    let mut results = Vec::new();
-    let mut total_capacity = 0;
-    for line in crate::data::DATA_GENESIS_FINAL.split('\n') {
-        if line.trim().is_empty() {
-            continue;
-        }
-        let parts = line.trim().split(',').collect::<Vec<_>>();
-        let address_str = parts[0];
-        let capacity_str = parts[1];
-        let datetime_str = parts[2];
-        let address = Address::from_input(address_str).unwrap().1;
-        let capacity = capacity_str.parse::<u64>().unwrap() * ONE_CKB;
-        let lock_script = match datetime_str {
-            "\"\"" | "" => {
-                packed::Script::new_builder()
-                    .code_hash(crate::consts::SECP_TYPE_SCRIPT_HASH.pack())
-                    .hash_type(ScriptHashType::Type.into())
-                    .args(Bytes::from(address.hash().as_bytes()).pack())
-                    .build()
-            },
-            value => {
-                let lock_arg: Bytes = crate::build_multisig_lock_arg(address, value, last_epoch).into();
-                packed::Script::new_builder()
+// Columns of `genesis_final.csv`, the header line is optional but must
+// follow this order when present. Only address and capacity are required.
+pub const COLUMNS: &[&str] = &["address", "capacity", "lock_until", "category", "comment"];
+
+pub struct GenesisRecord {
+    // Line number in the file
+    pub row: u64,
+    pub address: Address,
+    // Network of the address as written
+    pub network: NetworkType,
+    // In shannons
+    pub capacity: u64,
+    // `YYYY-MM-DD` or RFC 3339 datetime, a vesting schedule is expanded
+    // into one record per tranche
+    pub lock_until: Option<String>,
+    pub category: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl GenesisRecord {
+    pub fn lock_script(&self, last_epoch: u64) -> Result<packed::Script, String> {
+        match self.lock_until {
+            None => Ok(self.address.to_script()),
+            Some(ref value) => {
+                let lock_arg: Bytes =
+                    crate::build_multisig_lock_arg(self.address.clone(), value, last_epoch)?
+                        .into();
+                Ok(packed::Script::new_builder()
                     .code_hash(crate::consts::MULTISIG_TYPE_SCRIPT_HASH.pack())
                     .hash_type(ScriptHashType::Type.into())
                     .args(lock_arg.pack())
-                    .build()
+                    .build())
+            }
+        }
+    }
+}
+
+// Unit of the bare amounts in the signed release file, which can't be changed
+pub const RELEASE_CAPACITY_UNIT: &str = "CKB";
+
+// Capacity with unit: `100 CKB`, `0.5 CKB` or `100 shannon`. A bare amount is
+// rejected unless the source declares `default_unit`.
+pub fn parse_capacity(input: &str, default_unit: Option<&str>) -> Result<u64, String> {
+    let input = input.trim();
+    let (value, unit) = match (input.find(' '), default_unit) {
+        (Some(index), _) => (&input[..index], input[index..].trim()),
+        (None, Some(unit)) => (input, unit),
+        (None, None) => return Err(format!("missing capacity unit: {}", input)),
+    };
+    match unit {
+        "CKB" => {
+            let mut parts = value.splitn(2, '.');
+            let integer = parts.next().unwrap();
+            let fraction = parts.next().unwrap_or("");
+            if integer.is_empty()
+                || fraction.len() > 8
+                || !fraction.chars().all(|c| c.is_ascii_digit())
+            {
+                return Err(format!("invalid CKB amount: {}", value));
+            }
+            let integer = integer.parse::<u64>().map_err(|err| err.to_string())?;
+            let fraction = format!("{:0<8}", fraction).parse::<u64>().unwrap();
+            integer
+                .checked_mul(ONE_CKB)
+                .and_then(|shannons| shannons.checked_add(fraction))
+                .ok_or_else(|| format!("capacity overflow: {}", input))
+        }
+        "shannon" | "shannons" => value.parse::<u64>().map_err(|err| err.to_string()),
+        _ => Err(format!("invalid capacity unit: {}", unit)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Period {
+    Days(u32),
+    Months(u32),
+}
+
+impl Period {
+    // `30d`, `6m` or `1y`
+    pub fn parse(input: &str) -> Result<Period, String> {
+        let (value, unit) = input.split_at(
+            input
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(input.len()),
+        );
+        let value = value
+            .parse::<u32>()
+            .map_err(|err| format!("invalid period {}: {}", input, err))?;
+        match unit {
+            "d" => Ok(Period::Days(value)),
+            "m" => Ok(Period::Months(value)),
+            "y" => Ok(Period::Months(value * 12)),
+            _ => Err(format!("invalid period unit: {}", input)),
+        }
+    }
+
+    fn times(self, count: u32) -> Period {
+        match self {
+            Period::Days(days) => Period::Days(days * count),
+            Period::Months(months) => Period::Months(months * count),
+        }
+    }
+
+    // The day of month must exist in the target month
+    fn add_to(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Period::Days(days) => {
+                date.checked_add_signed(chrono::Duration::days(i64::from(days)))
+            }
+            Period::Months(months) => {
+                let month0 = date.month0() + months;
+                let year = date.year() + (month0 / 12) as i32;
+                NaiveDate::from_ymd_opt(year, month0 % 12 + 1, date.day())
+            }
+        }
+    }
+}
+
+// `vest:start=2020-05-01;cliff=12m;tranches=3;interval=12m` unlocks
+// `tranches` equal parts, the first at `start + cliff` and then every
+// `interval`. Parts are rounded to shannons and sum to the row capacity.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Vesting {
+    pub start: NaiveDate,
+    pub cliff: Period,
+    pub tranches: u32,
+    pub interval: Period,
+}
+
+impl Vesting {
+    pub fn parse(input: &str) -> Result<Vesting, String> {
+        let mut start = None;
+        let mut cliff = Period::Days(0);
+        let mut tranches = None;
+        let mut interval = None;
+        for item in input.trim_start_matches("vest:").split(';') {
+            let mut parts = item.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| format!("invalid vesting item: {}", item))?
+                .trim();
+            match key {
+                "start" => {
+                    start = Some(
+                        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                            .map_err(|err| format!("invalid start {}: {}", value, err))?,
+                    )
+                }
+                "cliff" => cliff = Period::parse(value)?,
+                "tranches" => {
+                    tranches = Some(
+                        value
+                            .parse::<u32>()
+                            .map_err(|err| format!("invalid tranches {}: {}", value, err))?,
+                    )
+                }
+                "interval" => interval = Some(Period::parse(value)?),
+                _ => return Err(format!("unknown vesting item: {}", key)),
+            }
+        }
+        let tranches = tranches.ok_or_else(|| "missing tranches".to_string())?;
+        if tranches == 0 {
+            return Err("tranches must be greater than 0".to_string());
+        }
+        Ok(Vesting {
+            start: start.ok_or_else(|| "missing start".to_string())?,
+            cliff,
+            tranches,
+            interval: match interval {
+                Some(interval) => interval,
+                None if tranches == 1 => Period::Days(0),
+                None => return Err("missing interval".to_string()),
             },
+        })
+    }
+
+    pub fn unlock_dates(&self) -> Result<Vec<NaiveDate>, String> {
+        let first = self
+            .cliff
+            .add_to(self.start)
+            .ok_or_else(|| format!("invalid cliff {:?} from {}", self.cliff, self.start))?;
+        (0..self.tranches)
+            .map(|index| {
+                self.interval
+                    .times(index)
+                    .add_to(first)
+                    .ok_or_else(|| format!("invalid tranche {} from {}", index, first))
+            })
+            .collect()
+    }
+
+    // (unlock date, capacity) of each tranche
+    pub fn expand(&self, capacity: u64) -> Result<Vec<(String, u64)>, String> {
+        let dates = self.unlock_dates()?;
+        let weights: Vec<(String, u64)> = dates
+            .iter()
+            .map(|date| (date.format("%Y-%m-%d").to_string(), 1))
+            .collect();
+        let shares = apportion(
+            &weights,
+            weights.len() as u64,
+            capacity,
+            Apportionment::BankersShannon,
+        );
+        Ok(shares.into_iter().map(|share| (share.key, share.amount)).collect())
+    }
+}
+
+fn parse_lock_until(input: &str) -> Result<(), String> {
+    if input.len() == 10 {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    } else {
+        DateTime::parse_from_rfc3339(input)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+// Lines starting with `#` are comments
+pub fn parse_records(
+    data: &str,
+    default_unit: Option<&str>,
+) -> Result<Vec<GenesisRecord>, String> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+    let mut records = Vec::new();
+    for (index, record) in rdr.records().enumerate() {
+        let record = record.map_err(|err| err.to_string())?;
+        let row = record.position().map(|position| position.line()).unwrap_or_default();
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        if record.len() < 2 || record.len() > COLUMNS.len() {
+            return Err(format!(
+                "row {}: expected 2 to {} columns, got {}",
+                row,
+                COLUMNS.len(),
+                record.len()
+            ));
+        }
+        if index == 0 && record.get(0) == Some(COLUMNS[0]) {
+            for (column, expected) in record.iter().zip(COLUMNS.iter()) {
+                if column != *expected {
+                    return Err(format!("row {}: expected column {}, got {}", row, expected, column));
+                }
+            }
+            continue;
+        }
+        let optional = |index: usize| {
+            record
+                .get(index)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
         };
-        total_capacity += capacity;
-        results.push((lock_script, capacity));
+        let address_str = record.get(0).unwrap();
+        let (network, address) = Address::from_input(address_str)
+            .map_err(|err| format!("row {}: invalid address {}: {}", row, address_str, err))?;
+        let capacity = parse_capacity(record.get(1).unwrap(), default_unit)
+            .map_err(|err| format!("row {}: invalid capacity: {}", row, err))?;
+        let lock_until = optional(2);
+        if lock_until.is_some() && address.hash().is_none() {
+            return Err(format!("row {}: time locked cells need a sighash address", row));
+        }
+        let tranches = match lock_until {
+            Some(ref value) if value.starts_with("vest:") => Vesting::parse(value)
+                .and_then(|vesting| vesting.expand(capacity))
+                .map_err(|err| format!("row {}: invalid vesting {}: {}", row, value, err))?
+                .into_iter()
+                .map(|(date, capacity)| (Some(date), capacity))
+                .collect(),
+            Some(ref value) => {
+                parse_lock_until(value)
+                    .map_err(|err| format!("row {}: invalid lock_until {}: {}", row, value, err))?;
+                vec![(lock_until.clone(), capacity)]
+            }
+            None => vec![(None, capacity)],
+        };
+        for (lock_until, capacity) in tranches {
+            records.push(GenesisRecord {
+                row,
+                address: address.clone(),
+                network,
+                capacity,
+                lock_until,
+                category: optional(3),
+                comment: optional(4),
+            });
+        }
+    }
+    Ok(records)
+}
+
+// Lock scripts are built by the caller once the time locks are checked
+pub fn read_all_records(
+    tags: &CategoryTags,
+    summary: &mut CategorySummary,
+    network_check: &mut NetworkCheck,
+) -> Vec<GenesisRecord> {
+    let records = parse_records(crate::data::DATA_GENESIS_FINAL, Some(RELEASE_CAPACITY_UNIT))
+        .unwrap_or_else(|err| panic!("genesis_final.csv {}", err));
+    let mut total_capacity = 0;
+    for record in &records {
+        network_check.expect(
+            &format!("genesis_final.csv row {}", record.row),
+            &record.address.to_string(record.network),
+            NETWORK,
+            record.network,
+        );
+        let category = tags
+            .category_of(record)
+            .unwrap_or_else(|err| panic!("genesis_final.csv {}", err));
+        log::debug!(
+            "row {}: {:?} => {}, {}",
+            record.row,
+            category,
+            record.capacity,
+            record.comment.as_ref().map(String::as_str).unwrap_or_default()
+        );
+        summary.add(category, record.lock_until.clone(), record.capacity);
+        total_capacity += record.capacity;
     }
     println!("genesis_final.total_capacity: {}", total_capacity);
-    results
+    records
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_capacity() {
+        assert_eq!(parse_capacity("112000000", Some("CKB")), Ok(112_000_000 * ONE_CKB));
+        assert!(parse_capacity("112000000", None).is_err());
+        assert_eq!(parse_capacity("100 CKB", None), Ok(100 * ONE_CKB));
+        assert_eq!(parse_capacity("0.5 CKB", None), Ok(ONE_CKB / 2));
+        assert_eq!(parse_capacity("61 shannon", Some("CKB")), Ok(61));
+        assert!(parse_capacity("0.000000001 CKB", None).is_err());
+        assert!(parse_capacity("100 KB", None).is_err());
+        assert!(parse_capacity("-1", Some("CKB")).is_err());
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let data = "address,capacity,lock_until,category,comment\n\
+                    # comment line\n\
+                    ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p,100 CKB,\"\"\n\
+                    ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p,1 shannon,2020-05-01,team,vesting\n";
+        let records = parse_records(data, None).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].row, 3);
+        assert_eq!(records[0].lock_until, None);
+        assert_eq!(records[1].capacity, 1);
+        assert_eq!(records[1].lock_until.as_ref().map(String::as_str), Some("2020-05-01"));
+        assert_eq!(records[1].category.as_ref().map(String::as_str), Some("team"));
+
+        let err = parse_records("ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p\n", None)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("row 1:"), "{}", err);
+        let err = parse_records("ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p,1\n", None)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("row 1: invalid capacity"), "{}", err);
+        let err = parse_records(
+            "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p,1 CKB,2020-13-01\n",
+            None,
+        )
+        .err()
+        .unwrap();
+        assert!(err.starts_with("row 1: invalid lock_until"), "{}", err);
+    }
+
+    #[test]
+    fn test_vesting() {
+        let vesting =
+            Vesting::parse("vest:start=2020-05-01;cliff=0m;tranches=3;interval=12m").unwrap();
+        let tranches = vesting.expand(100 * ONE_CKB + 1).unwrap();
+        assert_eq!(
+            tranches,
+            vec![
+                ("2020-05-01".to_string(), 3_333_333_333),
+                ("2021-05-01".to_string(), 3_333_333_334),
+                ("2022-05-01".to_string(), 3_333_333_334),
+            ]
+        );
+
+        let vesting =
+            Vesting::parse("vest:start=2019-11-29;cliff=3m;tranches=2;interval=90d").unwrap();
+        let dates: Vec<_> = vesting
+            .unlock_dates()
+            .unwrap()
+            .iter()
+            .map(|date| date.to_string())
+            .collect();
+        assert_eq!(dates, vec!["2020-02-29", "2020-05-29"]);
+
+        assert!(Vesting::parse("vest:start=2020-01-31;cliff=1m;tranches=1")
+            .unwrap()
+            .unlock_dates()
+            .is_err());
+        assert!(Vesting::parse("vest:start=2020-05-01;tranches=0").is_err());
+        assert!(Vesting::parse("vest:start=2020-05-01;tranches=2").is_err());
+
+        let data = "ckb1qyqp8eqad7ffy42ezmchkjyz54rhcqf8q9pqrn323p,10 shannon,\
+                    vest:start=2020-05-01;cliff=1y;tranches=4;interval=6m\n";
+        let records = parse_records(data, None).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records.iter().map(|record| record.capacity).sum::<u64>(), 10);
+        assert_eq!(records[3].lock_until.as_ref().map(String::as_str), Some("2022-11-01"));
+    }
+
+    #[test]
+    fn test_parse_release_file() {
+        let records =
+            parse_records(crate::data::DATA_GENESIS_FINAL, Some(RELEASE_CAPACITY_UNIT)).unwrap();
+        assert_eq!(records.len(), 340);
+    }
 }
@@ -1,24 +1,125 @@
 use chrono::prelude::*;
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
 use std::thread;
 use std::time::Duration;
 
-use ckb_jsonrpc_types::{BlockNumber, EpochNumber};
+use ckb_jsonrpc_types::{BlockNumber, BlockView, EpochNumber};
+use crate::apportion::{apportion, Apportionment, Share};
 use crate::client::HttpRpcClient;
 use ckb_types::{
-    core::{EpochNumberWithFraction, ScriptHashType},
+    core::EpochNumberWithFraction,
     packed,
     prelude::*,
     utilities::{compact_to_difficulty, difficulty_to_compact},
-    H160, H256, U256,
+    H256, U256,
 };
 
-use crate::consts::{ONE_CKB, SECP_TYPE_SCRIPT_HASH};
+use crate::consts::ONE_CKB;
+use crate::display_lock;
+use crate::exclusions::Exclusions;
+
+// Where the share of miners below the minimum reward goes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DroppedRewards {
+    // Left in the testnet foundation remainder
+    Foundation,
+    // Redistributed proportionally to the remaining miners
+    Redistribute,
+}
+
+impl FromStr for DroppedRewards {
+    type Err = String;
+    fn from_str(input: &str) -> Result<DroppedRewards, String> {
+        match input {
+            "foundation" => Ok(DroppedRewards::Foundation),
+            "redistribute" => Ok(DroppedRewards::Redistribute),
+            _ => Err(format!("Invalid dropped rewards policy: {}", input)),
+        }
+    }
+}
+
+// What a miner's share of the last round is weighted by
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Weighting {
+    Primary,
+    PrimarySecondary,
+    // primary + secondary + proposal reward + tx fee
+    FullReward,
+    BlockCount,
+    // Main chain blocks and uncle blocks
+    BlockUncleCount,
+}
+
+impl FromStr for Weighting {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Weighting, String> {
+        match input {
+            "primary" => Ok(Weighting::Primary),
+            "primary-secondary" => Ok(Weighting::PrimarySecondary),
+            "full-reward" => Ok(Weighting::FullReward),
+            "block-count" => Ok(Weighting::BlockCount),
+            "block-uncle-count" => Ok(Weighting::BlockUncleCount),
+            _ => Err(format!("Invalid weighting: {}", input)),
+        }
+    }
+}
+
+impl Weighting {
+    // What the minimum reward is compared with: the weight itself for reward
+    // weightings, the share of the final round pool for block counts.
+    pub fn reward(self, weight: u64, total_weight: u64) -> u64 {
+        match self {
+            Weighting::BlockCount | Weighting::BlockUncleCount => {
+                (u128::from(weight) * u128::from(crate::consts::FINAL_ROUND_REWARD)
+                    / u128::from(std::cmp::max(total_weight, 1))) as u64
+            }
+            _ => weight,
+        }
+    }
+}
+
+// Reward components and block counts of a miner in the last round
+#[derive(Clone, Debug, Default)]
+pub struct MinerStats {
+    pub blocks: u64,
+    pub uncles: u64,
+    pub primary: u64,
+    pub secondary: u64,
+    pub proposal_reward: u64,
+    pub tx_fee: u64,
+}
+
+impl MinerStats {
+    pub fn weight(&self, weighting: Weighting) -> u64 {
+        match weighting {
+            Weighting::Primary => self.primary,
+            Weighting::PrimarySecondary => self.primary + self.secondary,
+            Weighting::FullReward => {
+                self.primary + self.secondary + self.proposal_reward + self.tx_fee
+            }
+            Weighting::BlockCount => self.blocks,
+            Weighting::BlockUncleCount => self.blocks + self.uncles,
+        }
+    }
+}
 
 pub struct CurrentTestnetResult {
-    pub rewards: Vec<(H160, u64)>,
+    pub miner_stats: HashMap<packed::Script, MinerStats>,
+    pub weighting: Weighting,
+    // Weights of miners
+    pub rewards: Vec<(packed::Script, u64)>,
+    // Miners whose reward under the weighting is not greater than the minimum reward, with weights
+    pub dropped: Vec<(packed::Script, u64)>,
+    pub dropped_rewards: DroppedRewards,
+    // Total weight of all miners including dropped ones, and excluded ones
+    // when they are counted, so their share is not redistributed
+    pub total_weight: u64,
+    // Including base rewards of excluded miners when they are counted
     pub total_base_reward: u64,
+    pub excluded_base_reward: u64,
+    pub excluded_weight: u64,
     pub last_block_hash: H256,
     pub last_block_number: u64,
     // Last block's timestamp
@@ -29,19 +130,52 @@ pub struct CurrentTestnetResult {
 }
 
 impl CurrentTestnetResult {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        rewards: HashMap<H160, u64>,
-        total_base_reward: u64,
+        miner_stats: HashMap<packed::Script, MinerStats>,
+        weighting: Weighting,
+        min_reward: u64,
+        dropped_rewards: DroppedRewards,
+        excluded_base_reward: u64,
+        excluded_weight: u64,
         last_block_hash: H256,
         last_block_number: u64,
         last_timestamp: u64,
         mainnet_difficulty: u32,
         last_epoch_length: u64,
     ) -> Self {
-        let rewards: Vec<(H160, u64)> = rewards.into_iter().collect();
+        let mut rewards = Vec::new();
+        let mut dropped = Vec::new();
+        let total_weight = excluded_weight
+            + miner_stats.values().map(|stats| stats.weight(weighting)).sum::<u64>();
+        let mut total_base_reward = excluded_base_reward;
+        for (lock_script, stats) in &miner_stats {
+            let weight = stats.weight(weighting);
+            total_base_reward += stats.primary;
+            let reward = weighting.reward(weight, total_weight);
+            if reward <= min_reward {
+                println!(
+                    "WARN: reward not greater than {}CKB {} => {}",
+                    min_reward / ONE_CKB,
+                    display_lock(lock_script),
+                    reward
+                );
+                dropped.push((lock_script.clone(), weight));
+            } else {
+                rewards.push((lock_script.clone(), weight));
+            }
+        }
+        dropped.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
         CurrentTestnetResult {
+            miner_stats,
+            weighting,
             rewards,
+            dropped,
+            dropped_rewards,
+            total_weight,
             total_base_reward,
+            excluded_base_reward,
+            excluded_weight,
             last_block_hash,
             last_block_number,
             last_timestamp,
@@ -50,19 +184,30 @@ impl CurrentTestnetResult {
         }
     }
 
-    pub fn real_rewards(&self) -> Vec<(H160, u64)> {
-        self.rewards
-            .iter()
-            .map(|(lock_arg, reward)| {
-                let real_reward = (u128::from(*reward)
-                    * u128::from(crate::consts::FINAL_ROUND_REWARD)
-                    / u128::from(self.total_base_reward)) as u64;
-                (lock_arg.clone(), real_reward / ONE_CKB * ONE_CKB)
-            })
-            .collect()
+    pub fn real_rewards(&self, method: Apportionment) -> Vec<Share<packed::Script>> {
+        let mut rewards = self.rewards.clone();
+        rewards.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+        apportion(
+            &rewards,
+            self.apportion_weight(),
+            crate::consts::FINAL_ROUND_REWARD,
+            method,
+        )
+    }
+
+    pub fn dropped_weight(&self) -> u64 {
+        self.dropped.iter().map(|(_, weight)| weight).sum()
     }
 
-    pub fn map(&self) -> HashMap<H160, u64> {
+    // The total weight the final round pool is split by
+    pub fn apportion_weight(&self) -> u64 {
+        match self.dropped_rewards {
+            DroppedRewards::Foundation => self.total_weight,
+            DroppedRewards::Redistribute => self.total_weight - self.dropped_weight(),
+        }
+    }
+
+    pub fn map(&self) -> HashMap<packed::Script, u64> {
         self.rewards.iter().cloned().collect()
     }
 }
@@ -71,21 +216,27 @@ impl fmt::Display for CurrentTestnetResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "{{")?;
         writeln!(f, "  total_base_reward: {}", self.total_base_reward)?;
+        writeln!(f, "  excluded_base_reward: {}", self.excluded_base_reward)?;
+        writeln!(f, "  excluded_weight: {}", self.excluded_weight)?;
+        writeln!(f, "  weighting: {:?}, total_weight: {}", self.weighting, self.total_weight)?;
         writeln!(f, "  last_block_hash: {:#}", self.last_block_hash)?;
         writeln!(f, "  last_block_number: {}", self.last_block_number)?;
         writeln!(f, "  last_timestamp: {}", self.last_timestamp)?;
         writeln!(f, "  last_epoch_length: {}", self.last_epoch_length)?;
         writeln!(f, "  mainnet_difficulty: {:#x}", self.mainnet_difficulty)?;
         writeln!(f, "  rewards.len(): {}", self.rewards.len())?;
+        writeln!(f, "  dropped.len(): {}", self.dropped.len())?;
         let mut total_real_reward = 0;
-        for (lock_arg, reward) in &self.rewards {
+        for (lock_script, reward) in &self.rewards {
             let real_reward = (u128::from(*reward) * u128::from(crate::consts::FINAL_ROUND_REWARD)
-                / u128::from(self.total_base_reward)) as u64;
+                / u128::from(self.apportion_weight())) as u64;
             total_real_reward += real_reward;
             writeln!(
                 f,
-                "  > lock_arg: {:#}, reward: {}, real-reward: {}",
-                lock_arg, reward, real_reward
+                "  > lock: {}, weight: {}, real-reward: {}",
+                display_lock(lock_script),
+                reward,
+                real_reward
             )?;
         }
         writeln!(f, "  total_real_reward: {}", total_real_reward)?;
@@ -93,14 +244,29 @@ impl fmt::Display for CurrentTestnetResult {
     }
 }
 
-pub fn read_last_round(url: &str, last_epoch: u64, confirmations: u16) -> CurrentTestnetResult {
+#[allow(clippy::too_many_arguments)]
+pub fn read_last_round(
+    url: &str,
+    last_epoch: u64,
+    confirmations: u16,
+    policy: &DifficultyPolicy,
+    candidates: &[DifficultyPolicy],
+    weighting: Weighting,
+    min_reward: u64,
+    dropped_rewards: DroppedRewards,
+    exclusions: &mut Exclusions,
+    count_excluded_base_reward: bool,
+) -> CurrentTestnetResult {
+    // Fail before scanning the whole round
+    policy.check_window(candidates, last_epoch).unwrap();
     let mut client = HttpRpcClient::from_uri(url);
-    let mut rewards = HashMap::default();
+    let mut miner_stats: HashMap<packed::Script, MinerStats> = HashMap::default();
     let mut last_block_hash = H256::default();
     let mut last_block_number = 0;
     let mut last_timestamp = 0;
     let mut last_epoch_length = 0;
-    let mut total_base_reward = 0;
+    // Base reward of the whole round including excluded miners
+    let mut scanned_base_reward = 0;
     let mut tip_number = get_tip_block_number(&mut client);
     let current_epoch_number = client.get_current_epoch().call().unwrap().number.value();
     println!(
@@ -146,48 +312,45 @@ pub fn read_last_round(url: &str, last_epoch: u64, confirmations: u16) -> Curren
         last_timestamp = block.header.inner.timestamp.value();
         last_epoch_length = epoch.length();
 
-        let cellbase: packed::Transaction = block.transactions[0].clone().inner.into();
-        let lock_script = cellbase
-            .into_view()
-            .witnesses()
-            .get(0)
-            .map(|data| packed::CellbaseWitness::from_slice(&data.raw_data()).unwrap())
+        let lock_script = cellbase_lock(&block);
+        let cursor_hash = client
+            .get_block_hash(BlockNumber::from(number + 11))
+            .call()
             .unwrap()
-            .lock();
-
-        if lock_script.code_hash() == SECP_TYPE_SCRIPT_HASH.pack()
-            && lock_script.hash_type() == ScriptHashType::Type.into()
-            && lock_script.args().raw_data().len() == 20
-        {
-            let cursor_hash = client
-                .get_block_hash(BlockNumber::from(number + 11))
-                .call()
-                .unwrap()
-                .0
-                .unwrap();
-            let base_reward: u64 = client
-                .get_cellbase_output_capacity_details(cursor_hash)
-                .call()
-                .unwrap()
-                .0
-                .unwrap()
-                .primary
-                .value();
-            total_base_reward += base_reward;
-            let lock_arg = H160::from_slice(&lock_script.args().raw_data()).unwrap();
-            log::debug!(
-                "lock_arg: {:#}, block-number: {:05}, base-reward: {}",
-                lock_arg,
-                number,
-                base_reward
-            );
-            *rewards.entry(lock_arg).or_default() += base_reward;
-        } else {
-            log::error!(
-                "Invalid lock script: {}, block number: {}",
-                lock_script,
-                number
-            );
+            .0
+            .unwrap();
+        let details = client
+            .get_cellbase_output_capacity_details(cursor_hash)
+            .call()
+            .unwrap()
+            .0
+            .unwrap();
+        let base_reward: u64 = details.primary.value();
+        scanned_base_reward += base_reward;
+        log::debug!(
+            "lock: {}, block-number: {:05}, base-reward: {}",
+            display_lock(&lock_script),
+            number,
+            base_reward
+        );
+        let stats = miner_stats.entry(lock_script).or_default();
+        stats.blocks += 1;
+        stats.primary += base_reward;
+        stats.secondary += details.secondary.value();
+        stats.proposal_reward += details.proposal_reward.value();
+        stats.tx_fee += details.tx_fee.value();
+
+        // Uncle blocks are not in main chain, but the node keeps them
+        for uncle in &block.uncles {
+            let uncle_hash = uncle.header.hash.clone();
+            match client.get_block(uncle_hash.clone()).call().unwrap().0 {
+                Some(uncle_block) => {
+                    miner_stats.entry(cellbase_lock(&uncle_block)).or_default().uncles += 1;
+                }
+                None => {
+                    log::warn!("uncle block not found: {:#}, block number: {}", uncle_hash, number);
+                }
+            }
         }
     }
 
@@ -196,50 +359,33 @@ pub fn read_last_round(url: &str, last_epoch: u64, confirmations: u16) -> Curren
         Local::now(),
         last_block_number
     );
+    let (excluded_base_reward, excluded_weight) = exclude_miners(
+        &mut miner_stats,
+        exclusions,
+        weighting,
+        count_excluded_base_reward,
+    );
     for n in 1..=u64::from(confirmations) {
         println!("[{}] Waiting for {} confirmation", Local::now(), n);
         let number = last_block_number + n;
         tip_number = wait_until(&mut client, number, Some(tip_number), 100);
     }
 
-    rewards.retain(|lock_arg, capacity| {
-        if *capacity <= 1000 * ONE_CKB {
-            println!("WARN: reward not greater than 1000CKB {:#} => {}", lock_arg, capacity);
-        }
-        *capacity > 1000 * ONE_CKB
-    });
-    let mainnet_difficulty = {
-        let mut total_difficulty = U256::zero();
-        for offset in 0..4 {
-            let epoch_number = last_epoch - offset;
-            let compact_target = client
-                .get_epoch_by_number(EpochNumber::from(epoch_number))
-                .call()
-                .unwrap()
-                .0
-                .unwrap()
-                .compact_target
-                .value();
-            println!(
-                "[{}] Epoch {}, compact_target: {:#x} / {}",
-                Local::now(),
-                epoch_number,
-                compact_target,
-                compact_to_difficulty(compact_target),
-            );
-            total_difficulty += compact_to_difficulty(compact_target);
-        }
-        total_difficulty = total_difficulty / U256::from(4u32);
-        // total_difficulty * 1.5
-        total_difficulty = total_difficulty * U256::from(3u32) / U256::from(2u32);
-        total_difficulty = total_difficulty * U256::from(total_base_reward) / U256::from(crate::consts::FINAL_ROUND_REWARD);
-        println!("mainet difficulty: {}", total_difficulty);
-        difficulty_to_compact(total_difficulty)
-    };
+    let epoch_stats =
+        read_epoch_stats(&mut client, last_epoch, policy.max_window(candidates)).unwrap();
+    // Exclusions only change the reward split, not the consensus difficulty
+    let mainnet_difficulty = policy.derive(&epoch_stats, scanned_base_reward);
+    println!("mainet difficulty: {}", mainnet_difficulty);
+    print_difficulty_table(&epoch_stats, scanned_base_reward, policy, candidates);
+    let mainnet_difficulty = difficulty_to_compact(mainnet_difficulty);
 
     CurrentTestnetResult::new(
-        rewards,
-        total_base_reward,
+        miner_stats,
+        weighting,
+        min_reward,
+        dropped_rewards,
+        excluded_base_reward,
+        excluded_weight,
         last_block_hash,
         last_block_number,
         last_timestamp,
@@ -248,6 +394,335 @@ pub fn read_last_round(url: &str, last_epoch: u64, confirmations: u16) -> Curren
     )
 }
 
+// Removes excluded miners, returns their base reward and weight when they
+// are counted in the reward split, zeros otherwise
+fn exclude_miners(
+    miner_stats: &mut HashMap<packed::Script, MinerStats>,
+    exclusions: &mut Exclusions,
+    weighting: Weighting,
+    count_excluded_base_reward: bool,
+) -> (u64, u64) {
+    let mut excluded_base_reward = 0;
+    let mut excluded_weight = 0;
+    miner_stats.retain(|lock_script, stats| {
+        let excluded = exclusions.exclude("last-round", lock_script, stats.primary);
+        if excluded {
+            excluded_base_reward += stats.primary;
+            excluded_weight += stats.weight(weighting);
+        }
+        !excluded
+    });
+    if count_excluded_base_reward {
+        (excluded_base_reward, excluded_weight)
+    } else {
+        (0, 0)
+    }
+}
+
+// How the mainnet difficulty is derived from the last epochs of testnet
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DifficultyPolicy {
+    // Number of last epochs to average
+    pub window: u64,
+    // Multiplier as (numerator, denominator)
+    pub multiplier: (u64, u64),
+    // Scale by `total_base_reward / FINAL_ROUND_REWARD`
+    pub reward_scaling: bool,
+}
+
+impl Default for DifficultyPolicy {
+    fn default() -> DifficultyPolicy {
+        DifficultyPolicy {
+            window: 4,
+            multiplier: (3, 2),
+            reward_scaling: true,
+        }
+    }
+}
+
+impl DifficultyPolicy {
+    pub fn parse_multiplier(input: &str) -> Result<(u64, u64), String> {
+        let mut parts = input.splitn(2, '/');
+        let numerator = parts
+            .next()
+            .unwrap()
+            .trim()
+            .parse::<u64>()
+            .map_err(|err| err.to_string())?;
+        let denominator = match parts.next() {
+            Some(value) => value.trim().parse::<u64>().map_err(|err| err.to_string())?,
+            None => 1,
+        };
+        if numerator == 0 || denominator == 0 {
+            return Err(format!("Invalid multiplier: {}", input));
+        }
+        Ok((numerator, denominator))
+    }
+
+    // Candidate policies shown side by side in the what-if table
+    pub fn candidates() -> Vec<DifficultyPolicy> {
+        let mut candidates = Vec::new();
+        for window in &[1, 4, 8] {
+            for multiplier in &[(1, 1), (3, 2), (2, 1)] {
+                for reward_scaling in &[true, false] {
+                    candidates.push(DifficultyPolicy {
+                        window: *window,
+                        multiplier: *multiplier,
+                        reward_scaling: *reward_scaling,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+
+    pub fn max_window(&self, candidates: &[DifficultyPolicy]) -> u64 {
+        candidates
+            .iter()
+            .map(|policy| policy.window)
+            .chain(std::iter::once(self.window))
+            .max()
+            .unwrap()
+    }
+
+    // Epochs `last_epoch - window + 1 ..= last_epoch` must exist
+    pub fn check_window(
+        &self,
+        candidates: &[DifficultyPolicy],
+        last_epoch: u64,
+    ) -> Result<(), String> {
+        let window = self.max_window(candidates);
+        if window == 0 || window > last_epoch + 1 {
+            Err(format!("difficulty window {} is out of epochs 0..={}", window, last_epoch))
+        } else {
+            Ok(())
+        }
+    }
+
+    // `epoch_stats` is ordered from the last epoch backwards
+    pub fn derive(&self, epoch_stats: &[EpochStat], total_base_reward: u64) -> U256 {
+        let window = &epoch_stats[..self.window as usize];
+        let mut difficulty = U256::zero();
+        for stat in window {
+            difficulty += compact_to_difficulty(stat.compact_target);
+        }
+        difficulty = difficulty / U256::from(self.window);
+        difficulty = difficulty * U256::from(self.multiplier.0) / U256::from(self.multiplier.1);
+        if self.reward_scaling {
+            difficulty = difficulty * U256::from(total_base_reward)
+                / U256::from(crate::consts::FINAL_ROUND_REWARD);
+        }
+        difficulty
+    }
+}
+
+impl fmt::Display for DifficultyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "window={}, multiplier={}/{}, reward_scaling={}",
+            self.window, self.multiplier.0, self.multiplier.1, self.reward_scaling
+        )
+    }
+}
+
+pub struct EpochStat {
+    pub number: u64,
+    pub compact_target: u32,
+    // Average block interval in milliseconds
+    pub block_interval: u64,
+}
+
+fn read_epoch_stats(
+    client: &mut HttpRpcClient,
+    last_epoch: u64,
+    count: u64,
+) -> Result<Vec<EpochStat>, String> {
+    let mut stats = Vec::new();
+    for offset in 0..count {
+        let epoch_number = last_epoch.checked_sub(offset).ok_or_else(|| {
+            format!("difficulty window {} is out of epochs 0..={}", count, last_epoch)
+        })?;
+        let epoch = client
+            .get_epoch_by_number(EpochNumber::from(epoch_number))
+            .call()
+            .unwrap()
+            .0
+            .unwrap();
+        let compact_target = epoch.compact_target.value();
+        let start_number = epoch.start_number.value();
+        let length = epoch.length.value();
+        let timestamp_of = |client: &mut HttpRpcClient, number: u64| -> u64 {
+            client
+                .get_header_by_number(BlockNumber::from(number))
+                .call()
+                .unwrap()
+                .0
+                .unwrap()
+                .inner
+                .timestamp
+                .value()
+        };
+        if length == 0 {
+            return Err(format!("epoch {} has no blocks", epoch_number));
+        }
+        let start_timestamp = timestamp_of(client, start_number);
+        let end_timestamp = timestamp_of(client, start_number + length - 1);
+        let duration = end_timestamp.checked_sub(start_timestamp).ok_or_else(|| {
+            format!(
+                "epoch {} ends at {} before its start {}",
+                epoch_number, end_timestamp, start_timestamp
+            )
+        })?;
+        let block_interval = duration / std::cmp::max(length - 1, 1);
+        println!(
+            "[{}] Epoch {}, compact_target: {:#x} / {}, block interval: {}ms",
+            Local::now(),
+            epoch_number,
+            compact_target,
+            compact_to_difficulty(compact_target),
+            block_interval,
+        );
+        stats.push(EpochStat {
+            number: epoch_number,
+            compact_target,
+            block_interval,
+        });
+    }
+    Ok(stats)
+}
+
+// The expected block interval assumes the hash rate observed in the
+// configured policy's window stays the same on mainnet.
+fn print_difficulty_table(
+    epoch_stats: &[EpochStat],
+    total_base_reward: u64,
+    policy: &DifficultyPolicy,
+    candidates: &[DifficultyPolicy],
+) {
+    let window = &epoch_stats[..policy.window as usize];
+    let mut observed_difficulty = U256::zero();
+    let mut observed_interval = 0;
+    for stat in window {
+        observed_difficulty += compact_to_difficulty(stat.compact_target);
+        observed_interval += stat.block_interval;
+    }
+    let observed_difficulty = observed_difficulty / U256::from(policy.window);
+    let observed_interval = U256::from(observed_interval / policy.window);
+    println!(
+        "==== Difficulty policies (epochs {}..={}, observed interval: {}ms)",
+        window.last().unwrap().number,
+        window[0].number,
+        observed_interval,
+    );
+    for candidate in std::iter::once(policy).chain(candidates.iter()) {
+        let difficulty = candidate.derive(epoch_stats, total_base_reward);
+        let compact_target = difficulty_to_compact(difficulty.clone());
+        // Zero difficulty epochs, e.g. on a dev chain, have no expected interval
+        let expected_interval = if observed_difficulty == U256::zero() {
+            "n/a".to_string()
+        } else {
+            let interval = difficulty * observed_interval.clone() / observed_difficulty.clone();
+            format!("{}ms", interval)
+        };
+        println!(
+            "{} [{}] compact_target: {:#x}, difficulty: {}, expected interval: {}",
+            if candidate == policy { "*" } else { " " },
+            candidate,
+            compact_target,
+            compact_to_difficulty(compact_target),
+            expected_interval,
+        );
+    }
+}
+
+pub fn print_miner_stats_report(result: &CurrentTestnetResult) {
+    println!(
+        "==== Last round miners: {}, weighting: {:?}",
+        result.miner_stats.len(),
+        result.weighting
+    );
+    let mut miner_stats: Vec<_> = result.miner_stats.iter().collect();
+    miner_stats.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+    for (lock_script, stats) in miner_stats {
+        println!(
+            "  > lock: {}, blocks: {}, uncles: {}, primary: {}, secondary: {}, proposal: {}, tx_fee: {}, weight: {}",
+            display_lock(lock_script),
+            stats.blocks,
+            stats.uncles,
+            stats.primary,
+            stats.secondary,
+            stats.proposal_reward,
+            stats.tx_fee,
+            stats.weight(result.weighting),
+        );
+    }
+}
+
+pub fn print_dropped_report(result: &CurrentTestnetResult) {
+    println!(
+        "==== Last round dropped miners: {}, policy: {:?}",
+        result.dropped.len(),
+        result.dropped_rewards
+    );
+    for (lock_script, weight) in &result.dropped {
+        println!(
+            "  > lock: {}, base-reward: {}, weight: {}, reward: {}",
+            display_lock(lock_script),
+            result.miner_stats[lock_script].primary,
+            weight,
+            result.weighting.reward(*weight, result.total_weight)
+        );
+    }
+    let dropped_weight = result.dropped_weight();
+    let dropped_share = (u128::from(dropped_weight)
+        * u128::from(crate::consts::FINAL_ROUND_REWARD)
+        / u128::from(result.total_weight)) as u64;
+    println!(
+        "  dropped weight: {}, share of pool: {}",
+        dropped_weight, dropped_share
+    );
+}
+
+pub fn print_apportionment_report(shares: &[Share<packed::Script>], method: Apportionment) {
+    println!("==== Last round apportionment: {}", method);
+    if shares.is_empty() {
+        println!("  WARN: no miner weight, the pool is not distributed");
+    }
+    let mut total_exact = 0;
+    let mut total_reward = 0;
+    for share in shares {
+        total_exact += share.exact;
+        total_reward += share.amount;
+        println!(
+            "  > lock: {}, weight: {}, exact: {}, reward: {}, delta: {}",
+            display_lock(&share.key),
+            share.weight,
+            share.exact,
+            share.amount,
+            share.delta()
+        );
+    }
+    println!(
+        "  pool: {}, total_exact: {}, total_reward: {}, residue: {}",
+        crate::consts::FINAL_ROUND_REWARD,
+        total_exact,
+        total_reward,
+        crate::consts::FINAL_ROUND_REWARD as i64 - total_reward as i64
+    );
+}
+
+fn cellbase_lock(block: &BlockView) -> packed::Script {
+    let cellbase: packed::Transaction = block.transactions[0].clone().inner.into();
+    cellbase
+        .into_view()
+        .witnesses()
+        .get(0)
+        .map(|data| packed::CellbaseWitness::from_slice(&data.raw_data()).unwrap())
+        .unwrap()
+        .lock()
+}
+
 fn get_tip_block_number(client: &mut HttpRpcClient) -> u64 {
     client.get_tip_block_number().call().unwrap().value()
 }
@@ -273,3 +748,124 @@ fn wait_until(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::exclusions::{Exclusion, ExclusionScope};
+
+    #[test]
+    fn test_check_window() {
+        let policy = DifficultyPolicy::default();
+        assert!(policy.check_window(&[], 3).is_ok());
+        assert!(policy.check_window(&[], 2).is_err());
+        assert!(policy.check_window(&DifficultyPolicy::candidates(), 7).is_ok());
+        assert!(policy.check_window(&DifficultyPolicy::candidates(), 6).is_err());
+    }
+
+    fn lock(byte: u8) -> packed::Script {
+        packed::Script::new_builder()
+            .args(ckb_types::bytes::Bytes::from(vec![byte; 20]).pack())
+            .build()
+    }
+
+    fn miner_stats() -> HashMap<packed::Script, MinerStats> {
+        let mut miner_stats = HashMap::new();
+        miner_stats.insert(
+            lock(1),
+            MinerStats {
+                blocks: 10,
+                primary: 10_000 * ONE_CKB,
+                ..Default::default()
+            },
+        );
+        // Uncle only miner
+        miner_stats.insert(
+            lock(2),
+            MinerStats {
+                uncles: 10,
+                ..Default::default()
+            },
+        );
+        miner_stats
+    }
+
+    fn result(weighting: Weighting, excluded_weight: u64) -> CurrentTestnetResult {
+        CurrentTestnetResult::new(
+            miner_stats(),
+            weighting,
+            1000 * ONE_CKB,
+            DroppedRewards::Foundation,
+            0,
+            excluded_weight,
+            H256::default(),
+            0,
+            0,
+            0,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_min_reward_weighting() {
+        let primary = result(Weighting::Primary, 0);
+        assert_eq!(primary.dropped, vec![(lock(2), 0)]);
+
+        let block_uncle_count = result(Weighting::BlockUncleCount, 0);
+        assert!(block_uncle_count.dropped.is_empty());
+        assert_eq!(block_uncle_count.rewards.len(), 2);
+    }
+
+    #[test]
+    fn test_counted_excluded_weight() {
+        let total = |result: &CurrentTestnetResult| {
+            result
+                .real_rewards(Apportionment::BankersShannon)
+                .iter()
+                .map(|share| share.amount)
+                .sum::<u64>()
+        };
+        let all = result(Weighting::Primary, 0);
+        assert_eq!(total(&all), crate::consts::FINAL_ROUND_REWARD);
+        // The excluded share is left to the testnet foundation
+        let counted = result(Weighting::Primary, 10_000 * ONE_CKB);
+        assert_eq!(counted.total_weight, 20_000 * ONE_CKB);
+        assert_eq!(total(&counted), crate::consts::FINAL_ROUND_REWARD / 2);
+    }
+
+    #[test]
+    fn test_difficulty_ignores_exclusions() {
+        let epoch_stats: Vec<EpochStat> = (0..4)
+            .map(|number| EpochStat {
+                number,
+                compact_target: 0x1d08_0000,
+                block_interval: 8000,
+            })
+            .collect();
+        let policy = DifficultyPolicy::default();
+        let scanned = |miner_stats: &HashMap<packed::Script, MinerStats>| {
+            miner_stats.values().map(|stats| stats.primary).sum::<u64>()
+        };
+        let expected = policy.derive(&epoch_stats, scanned(&miner_stats()));
+        for count_excluded_base_reward in &[true, false] {
+            let mut miner_stats = miner_stats();
+            let scanned_base_reward = scanned(&miner_stats);
+            let mut exclusions = Exclusions::new(vec![Exclusion {
+                line: 2,
+                address: display_lock(&lock(1)),
+                lock_script: lock(1),
+                scope: ExclusionScope::All,
+                reason: "test".to_string(),
+            }]);
+            let (excluded_base_reward, _) = exclude_miners(
+                &mut miner_stats,
+                &mut exclusions,
+                Weighting::Primary,
+                *count_excluded_base_reward,
+            );
+            assert_eq!(miner_stats.len(), 1);
+            assert_eq!(excluded_base_reward > 0, *count_excluded_base_reward);
+            assert_eq!(policy.derive(&epoch_stats, scanned_base_reward), expected);
+        }
+    }
+}
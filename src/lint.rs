@@ -0,0 +1,114 @@
+use ckb_chain_spec::ChainSpec;
+use ckb_types::{packed, prelude::*, H256};
+
+use std::collections::HashMap;
+
+use crate::display_lock;
+
+// Where an issued cell comes from, parallel to `spec.genesis.issued_cells`
+pub struct Provenance {
+    pub source: &'static str,
+    pub detail: String,
+}
+
+impl Provenance {
+    pub fn new(source: &'static str, detail: String) -> Provenance {
+        Provenance { source, detail }
+    }
+}
+
+// Developer locks issued in the testnet spec, they must not get mainnet allocations
+pub fn testnet_developer_locks() -> Vec<packed::Script> {
+    let testnet_spec: ChainSpec = toml::from_str(crate::data::TESTNET_SPEC).unwrap();
+    testnet_spec
+        .genesis
+        .issued_cells
+        .into_iter()
+        .map(|cell| packed::Script::from(cell.lock))
+        .filter(|lock| {
+            let code_hash: H256 = lock.code_hash().unpack();
+            code_hash != H256::default()
+        })
+        .collect()
+}
+
+// Flags allocations to the zero hash, burn locks outside `burn-cells.toml`, the
+// reserved genesis locks, testnet developer locks, and the same lock issued
+// from different sources.
+pub fn lint_issued_cells(spec: &ChainSpec, provenances: &[Provenance]) -> Vec<String> {
+    assert_eq!(spec.genesis.issued_cells.len(), provenances.len());
+    let reserved_locks: Vec<(&str, packed::Script)> = vec![
+        ("genesis_cell.lock", spec.genesis.genesis_cell.lock.clone().into()),
+        ("system_cells_lock", spec.genesis.system_cells_lock.clone().into()),
+        ("bootstrap_lock", spec.genesis.bootstrap_lock.clone().into()),
+    ];
+    let burn_locks: Vec<packed::Script> =
+        crate::burn::burn_cells().into_iter().map(|(lock, _)| lock).collect();
+    let developer_locks = testnet_developer_locks();
+
+    let mut issues = Vec::new();
+    let mut by_lock: HashMap<packed::Script, Vec<usize>> = HashMap::new();
+    for (index, (cell, provenance)) in spec
+        .genesis
+        .issued_cells
+        .iter()
+        .zip(provenances.iter())
+        .enumerate()
+    {
+        let lock: packed::Script = cell.lock.clone().into();
+        let code_hash: H256 = lock.code_hash().unpack();
+        let describe = |issue: String| {
+            format!(
+                "issued cell #{} ({} {}): {}, capacity: {}",
+                index, provenance.source, provenance.detail, issue, cell.capacity
+            )
+        };
+        if let Some((name, _)) = reserved_locks.iter().find(|(_, reserved)| reserved == &lock) {
+            issues.push(describe(format!("reserved lock {}", name)));
+        } else if burn_locks.contains(&lock) {
+            if provenance.source != "burn" {
+                issues.push(describe(format!("burn lock {}", display_lock(&lock))));
+            }
+        } else if code_hash == H256::default() {
+            issues.push(describe(format!("zero code hash lock {}", display_lock(&lock))));
+        }
+        if developer_locks.contains(&lock) {
+            issues.push(describe(format!("testnet developer lock {}", display_lock(&lock))));
+        }
+        by_lock.entry(lock).or_default().push(index);
+    }
+
+    let mut duplicates: Vec<_> = by_lock
+        .into_iter()
+        .filter(|(_, indexes)| {
+            indexes
+                .iter()
+                .any(|index| provenances[*index].source != provenances[indexes[0]].source)
+        })
+        .collect();
+    duplicates.sort_by_key(|(_, indexes)| indexes[0]);
+    for (lock, indexes) in duplicates {
+        let sources: Vec<String> = indexes
+            .iter()
+            .map(|index| {
+                format!(
+                    "#{} {} {}",
+                    index, provenances[*index].source, provenances[*index].detail
+                )
+            })
+            .collect();
+        issues.push(format!(
+            "lock {} issued from different sources: {}",
+            display_lock(&lock),
+            sources.join(", ")
+        ));
+    }
+    issues
+}
+
+pub fn print_lint_report(issues: &[String]) {
+    println!("==== Issued cells lint: {}", issues.len());
+    for issue in issues {
+        println!("  > {}", issue);
+    }
+}
@@ -0,0 +1,129 @@
+use ckb_jsonrpc_types::Script as JsonScript;
+use ckb_types::{core::ScriptHashType, packed, prelude::*};
+use serde_derive::Deserialize;
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::consts::{MULTISIG_TYPE_SCRIPT_HASH, SECP_TYPE_SCRIPT_HASH};
+
+// Example:
+//
+// [[mappings]]
+// testnet = { code_hash = "0x...", hash_type = "data", args = "0x..." }
+// mainnet = { code_hash = "0x...", hash_type = "type", args = "0x..." }
+#[derive(Deserialize)]
+struct LockMappingFile {
+    mappings: Vec<LockMappingEntry>,
+}
+
+#[derive(Deserialize)]
+struct LockMappingEntry {
+    testnet: JsonScript,
+    mainnet: JsonScript,
+}
+
+// Map testnet lock scripts to mainnet lock scripts. Locks of the system
+// scripts have the same type hash on both networks and are kept as is.
+#[derive(Default)]
+pub struct LockMapping {
+    mappings: HashMap<packed::Script, packed::Script>,
+}
+
+impl LockMapping {
+    pub fn load(path: &str) -> Result<LockMapping, String> {
+        let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let file: LockMappingFile = toml::from_str(&content).map_err(|err| err.to_string())?;
+        let mut mappings = HashMap::default();
+        for entry in file.mappings {
+            let testnet: packed::Script = entry.testnet.into();
+            let mainnet: packed::Script = entry.mainnet.into();
+            if mappings.insert(testnet.clone(), mainnet).is_some() {
+                return Err(format!("Duplicated testnet lock in {}: {}", path, testnet));
+            }
+        }
+        Ok(LockMapping { mappings })
+    }
+
+    // Locks already on mainnet, such as redirect targets, are kept as is. An
+    // explicit mapping to another lock conflicts with it.
+    pub fn keep(&mut self, lock_script: packed::Script) -> Result<(), String> {
+        match self.mappings.get(&lock_script) {
+            Some(mainnet) if mainnet != &lock_script => Err(format!(
+                "Lock {} is kept as is but mapped to {}",
+                lock_script, mainnet
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.mappings.insert(lock_script.clone(), lock_script);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn map(&self, lock_script: &packed::Script) -> Result<packed::Script, String> {
+        if let Some(mainnet) = self.mappings.get(lock_script) {
+            return Ok(mainnet.clone());
+        }
+        if lock_script.hash_type() != ScriptHashType::Type.into() {
+            return Err(format!("No mainnet lock mapping for: {}", lock_script));
+        }
+        // sighash: blake160, multisig: blake160 with optional 8 bytes since
+        let args_len = lock_script.args().raw_data().len();
+        let valid_args = if lock_script.code_hash() == SECP_TYPE_SCRIPT_HASH.pack() {
+            Some(args_len == 20)
+        } else if lock_script.code_hash() == MULTISIG_TYPE_SCRIPT_HASH.pack() {
+            Some(args_len == 20 || args_len == 28)
+        } else {
+            None
+        };
+        match valid_args {
+            Some(true) => Ok(lock_script.clone()),
+            Some(false) => Err(format!(
+                "Invalid args length {} of system lock: {}",
+                args_len, lock_script
+            )),
+            None => Err(format!("No mainnet lock mapping for: {}", lock_script)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::{bytes::Bytes, H256};
+
+    fn system_lock(code_hash: &H256, args_len: usize) -> packed::Script {
+        packed::Script::new_builder()
+            .code_hash(code_hash.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![1u8; args_len]).pack())
+            .build()
+    }
+
+    #[test]
+    fn test_system_lock_args() {
+        let mapping = LockMapping::default();
+        assert!(mapping.map(&system_lock(&SECP_TYPE_SCRIPT_HASH, 20)).is_ok());
+        assert!(mapping.map(&system_lock(&SECP_TYPE_SCRIPT_HASH, 28)).is_err());
+        assert!(mapping.map(&system_lock(&SECP_TYPE_SCRIPT_HASH, 0)).is_err());
+        assert!(mapping.map(&system_lock(&MULTISIG_TYPE_SCRIPT_HASH, 20)).is_ok());
+        assert!(mapping.map(&system_lock(&MULTISIG_TYPE_SCRIPT_HASH, 28)).is_ok());
+        assert!(mapping.map(&system_lock(&MULTISIG_TYPE_SCRIPT_HASH, 21)).is_err());
+    }
+
+    #[test]
+    fn test_keep_conflict() {
+        let kept = system_lock(&SECP_TYPE_SCRIPT_HASH, 20);
+        let mut mapping = LockMapping::default();
+        assert!(mapping.keep(kept.clone()).is_ok());
+        assert!(mapping.keep(kept.clone()).is_ok());
+        assert_eq!(mapping.map(&kept), Ok(kept.clone()));
+
+        let mut mapping = LockMapping::default();
+        mapping
+            .mappings
+            .insert(kept.clone(), system_lock(&MULTISIG_TYPE_SCRIPT_HASH, 20));
+        assert!(mapping.keep(kept).is_err());
+    }
+}
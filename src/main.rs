@@ -1,7 +1,15 @@
 use ckb_chain_spec::{ChainSpec, IssuedCell};
 use basic::{Address, NetworkType, OldAddress};
 use ckb_types::{bytes::Bytes, core::{Capacity, ScriptHashType}, packed, prelude::*, H160, H256, core::EpochNumberWithFraction};
+use apportion::Apportionment;
+use categories::{Category, CategorySummary, CategoryTags};
 use clap::{App, AppSettings, Arg};
+use exclusions::Exclusions;
+use last_round::{DifficultyPolicy, DroppedRewards, Weighting};
+use lint::Provenance;
+use lock_mapping::LockMapping;
+use network_check::NetworkCheck;
+use recovery::{AddressRecovery, LockHashIndex};
 use ckb_hash::blake2b_256;
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -9,19 +17,36 @@ use chrono::prelude::*;
 use std::fs;
 use std::io::{Read, Write};
 
+mod adjustments;
+mod apportion;
+mod categories;
 mod consts;
 mod data;
+mod exclusions;
 mod last_round;
+mod genesis_capacity;
 mod genesis_final;
 mod previous_rounds;
+mod recovery;
+mod redirects;
+mod round_checks;
+mod time_locks;
 mod basic;
+mod burn;
+mod checks;
 mod client;
+mod foundation;
+mod lint;
+mod lock_mapping;
+mod network_check;
+
+const FINAL_SPEC_PATH: &str = "final-spec.toml";
 
 // TODO Tasks:
 //   [ ] read other sighash_all_records
 //   [ ] read other multisig_all_records
 //   [x] spec: difficulty, timestamp
-//   [ ] The rest cell for fundation cell
+//   [x] The rest cell for fundation cell
 
 //  TODO Checks:
 //   [ ] Check Genesis Allocation => 33.6
@@ -63,11 +88,198 @@ fn main() {
                 })
                 .help("Confirmations of block for security"),
         )
+        .arg(
+            Arg::with_name("lock-mapping")
+                .long("lock-mapping")
+                .takes_value(true)
+                .help("TOML file maps testnet lock scripts (other than sighash/multisig) to mainnet lock scripts"),
+        )
+        .arg(
+            Arg::with_name("lock-hash-index")
+                .long("lock-hash-index")
+                .takes_value(true)
+                .help("CSV file (lock_hash,args[,source]) to recover round data rows without address"),
+        )
+        .arg(
+            Arg::with_name("adjustments")
+                .long("adjustments")
+                .takes_value(true)
+                .help("CSV file (address,kind,amount,reason,approver) of manual adjustments applied after merging rounds"),
+        )
+        .arg(
+            Arg::with_name("redirects")
+                .long("redirects")
+                .takes_value(true)
+                .help("CSV file (from,to,signature) of signed reward redirect requests"),
+        )
+        .arg(
+            Arg::with_name("exclusions")
+                .long("exclusions")
+                .takes_value(true)
+                .help("CSV file (address,scope,reason) of disqualified testnet miners"),
+        )
+        .arg(
+            Arg::with_name("no-excluded-base-reward")
+                .long("no-excluded-base-reward")
+                .help("Don't count excluded last round miners in `total_base_reward` and the total weight, their share is redistributed"),
+        )
+        .arg(
+            Arg::with_name("categories")
+                .long("categories")
+                .takes_value(true)
+                .help("CSV file (address,category) tagging genesis_final.csv rows with allocation categories"),
+        )
+        .arg(
+            Arg::with_name("network-check")
+                .long("network-check")
+                .takes_value(true)
+                .default_value("strict")
+                .possible_values(&["strict", "warn"])
+                .help("Fail or warn when an address is not of the network its source expects"),
+        )
+        .arg(
+            Arg::with_name("unlock-drift-tolerance")
+                .long("unlock-drift-tolerance")
+                .takes_value(true)
+                .default_value("24")
+                .validator(|input| {
+                    input
+                        .parse::<u64>()
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .help("Warn when the estimated unlock time drifts more than this (in hours) from the lock date"),
+        )
+        .arg(
+            Arg::with_name("apportionment")
+                .long("apportionment")
+                .takes_value(true)
+                .default_value("floor")
+                .possible_values(&["floor", "largest-remainder", "bankers-ckb", "bankers-shannon"])
+                .help("How the final testnet round reward pool is split between miners"),
+        )
+        .arg(
+            Arg::with_name("weighting")
+                .long("weighting")
+                .takes_value(true)
+                .default_value("primary")
+                .possible_values(&[
+                    "primary",
+                    "primary-secondary",
+                    "full-reward",
+                    "block-count",
+                    "block-uncle-count",
+                ])
+                .help("What a miner's share of the last round is weighted by"),
+        )
+        .arg(
+            Arg::with_name("min-reward")
+                .long("min-reward")
+                .takes_value(true)
+                .default_value("1000")
+                .validator(|input| {
+                    input
+                        .parse::<u64>()
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .help("Last round miners with reward not greater than this (in CKB) are dropped, the reward is the share of the pool for block count weightings"),
+        )
+        .arg(
+            Arg::with_name("dropped-rewards")
+                .long("dropped-rewards")
+                .takes_value(true)
+                .default_value("foundation")
+                .possible_values(&["foundation", "redistribute"])
+                .help("Leave dropped miners' share to testnet foundation or redistribute it to other miners"),
+        )
+        .arg(
+            Arg::with_name("difficulty-window")
+                .long("difficulty-window")
+                .takes_value(true)
+                .default_value("4")
+                .validator(|input| match input.parse::<u64>() {
+                    Ok(0) => Err("must be greater than 0".to_owned()),
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(err.to_string()),
+                })
+                .help("Number of last testnet epochs to average for mainnet difficulty"),
+        )
+        .arg(
+            Arg::with_name("difficulty-multiplier")
+                .long("difficulty-multiplier")
+                .takes_value(true)
+                .default_value("3/2")
+                .validator(|input| DifficultyPolicy::parse_multiplier(&input).map(|_| ()))
+                .help("Multiplier applied to the average difficulty, as `numerator/denominator`"),
+        )
+        .arg(
+            Arg::with_name("no-reward-scaling")
+                .long("no-reward-scaling")
+                .help("Don't scale difficulty by `total_base_reward / FINAL_ROUND_REWARD`"),
+        )
+        .arg(
+            Arg::with_name("difficulty-what-if")
+                .long("difficulty-what-if")
+                .help("Show the difficulty derived by candidate policies side by side"),
+        )
         .get_matches();
 
     let testnet_rpc_server = matches.value_of("testnet-rpc-server").unwrap();
     let last_epoch = matches.value_of("last-epoch").unwrap().parse::<u64>().unwrap();
     let confirmations: u16 = matches.value_of("confirmations").unwrap().parse().unwrap();
+    let mut lock_mapping = matches
+        .value_of("lock-mapping")
+        .map(|path| LockMapping::load(path).unwrap())
+        .unwrap_or_default();
+    let mut recovery = AddressRecovery::new(
+        matches
+            .value_of("lock-hash-index")
+            .map(|path| LockHashIndex::load(path).unwrap()),
+    );
+    let mut network_check = NetworkCheck::new(matches.value_of("network-check") == Some("strict"));
+    let adjustments = matches
+        .value_of("adjustments")
+        .map(|path| adjustments::load_adjustments(path, &mut network_check).unwrap())
+        .unwrap_or_default();
+    let redirects = matches
+        .value_of("redirects")
+        .map(|path| redirects::load_redirects(path).unwrap())
+        .unwrap_or_default();
+    redirects::check_networks(&redirects, &mut network_check);
+    for redirect in &redirects {
+        if let Ok((_, to)) = redirect.verify() {
+            lock_mapping.keep(to).unwrap();
+        }
+    }
+    let mut exclusions = matches
+        .value_of("exclusions")
+        .map(|path| Exclusions::load(path, &mut network_check).unwrap())
+        .unwrap_or_default();
+    let count_excluded_base_reward = !matches.is_present("no-excluded-base-reward");
+    let category_tags = matches
+        .value_of("categories")
+        .map(|path| CategoryTags::load(path, &mut network_check).unwrap())
+        .unwrap_or_default();
+    let drift_tolerance =
+        matches.value_of("unlock-drift-tolerance").unwrap().parse::<u64>().unwrap() * 3600;
+    let apportionment: Apportionment = matches.value_of("apportionment").unwrap().parse().unwrap();
+    let weighting: Weighting = matches.value_of("weighting").unwrap().parse().unwrap();
+    let min_reward = matches.value_of("min-reward").unwrap().parse::<u64>().unwrap() * consts::ONE_CKB;
+    let dropped_rewards: DroppedRewards = matches.value_of("dropped-rewards").unwrap().parse().unwrap();
+    let difficulty_policy = DifficultyPolicy {
+        window: matches.value_of("difficulty-window").unwrap().parse().unwrap(),
+        multiplier: DifficultyPolicy::parse_multiplier(
+            matches.value_of("difficulty-multiplier").unwrap(),
+        )
+        .unwrap(),
+        reward_scaling: !matches.is_present("no-reward-scaling"),
+    };
+    let difficulty_candidates = if matches.is_present("difficulty-what-if") {
+        DifficultyPolicy::candidates()
+    } else {
+        Vec::new()
+    };
 
     {
         let mut boyu_file = fs::File::open("boyu-spec.toml").unwrap();
@@ -80,66 +292,203 @@ fn main() {
     }
 
     let mut spec: ChainSpec = toml::from_str(data::CHAIN_CHAIN_SPEC).unwrap();
+    // Where each issued cell comes from, for the lint report
+    let mut provenances = Vec::new();
+    for (index, (lock_script, capacity)) in burn::burn_cells().into_iter().enumerate() {
+        spec.genesis.issued_cells.push(IssuedCell {
+            capacity: Capacity::shannons(capacity),
+            lock: lock_script.into(),
+        });
+        provenances.push(Provenance::new("burn", format!("burn-cells.toml #{}", index)));
+    }
+    {
+        // Issued locks are built from the type hashes in `consts.rs`, fail
+        // before building any of them if the system cells don't produce them.
+        // The system cells don't depend on issued cells.
+        let preview_consensus = spec.build_consensus().unwrap();
+        let genesis_scripts =
+            checks::GenesisScripts::from_consensus(&spec, &preview_consensus).unwrap();
+        genesis_scripts.check_consts().unwrap();
+    }
+
+    // == Check historical round data before scanning testnet
+    let lock_hash_issues = round_checks::print_lock_hash_report();
+    if lock_hash_issues > 0 {
+        println!("WARN: {} rows with mismatched lock hash", lock_hash_issues);
+    }
+    round_checks::print_epoch_lucky_report(&recovery);
+    let reward_issues = round_checks::print_reward_report();
+    if reward_issues > 0 {
+        println!("WARN: {} rows with unexpected proportional reward", reward_issues);
+    }
+
+    // == Check addresses of all inputs before scanning testnet
+    let previous_rounds = previous_rounds::read_previous_rounds(&mut recovery, &mut network_check);
+    let mut category_summary = CategorySummary::default();
+    let genesis_final_records = genesis_final::read_all_records(
+        &category_tags,
+        &mut category_summary,
+        &mut network_check,
+    );
+    let categories_tagged = !category_tags.is_empty()
+        || genesis_final_records.iter().any(|record| record.category.is_some());
+    network_check.print_report();
+    network_check.check().unwrap();
 
     // == Testnet rewards
-    let testnet_result = previous_rounds::all_rewards(testnet_rpc_server, last_epoch, confirmations);
-    // == Other records
-    let genesis_final_records = genesis_final::read_all_records(last_epoch);
+    let testnet_result = previous_rounds::all_rewards(
+        testnet_rpc_server,
+        last_epoch,
+        confirmations,
+        &difficulty_policy,
+        &difficulty_candidates,
+        apportionment,
+        weighting,
+        min_reward,
+        dropped_rewards,
+        &mut exclusions,
+        count_excluded_base_reward,
+        previous_rounds,
+        &recovery,
+        &adjustments,
+        &redirects,
+    );
+
+    spec.genesis.timestamp = testnet_result.last_timestamp;
+    spec.genesis.genesis_cell.message = format!("lina {:#x}", testnet_result.last_block_hash);
+    spec.genesis.compact_target = testnet_result.mainnet_difficulty;
+    spec.params.genesis_epoch_length = testnet_result.last_epoch_length;
+    println!(">> timestamp: {}", spec.genesis.timestamp);
+    println!(">> message: {}", spec.genesis.genesis_cell.message);
+    println!(">> compact_target: {:#x}", spec.genesis.compact_target);
+    println!(">> genesis_epoch_length: {:#x}", spec.params.genesis_epoch_length);
+
+    // == Time locks must unlock after genesis, in date order, checked on the
+    // dates before any time locked script is built
+    let mut time_locks: Vec<(String, String)> = genesis_final_records
+        .iter()
+        .filter_map(|record| {
+            record
+                .lock_until
+                .clone()
+                .map(|date| (format!("genesis_final.csv row {}", record.row), date))
+        })
+        .collect();
+    time_locks.push((
+        "foundation reserve".to_string(),
+        crate::consts::FOUNDATION_RESERVE_LOCK_TIME.to_string(),
+    ));
+    let time_lock_check = time_locks::check_time_locks(
+        &time_locks,
+        spec.genesis.timestamp,
+        last_epoch,
+        drift_tolerance,
+    );
+    time_lock_check.print_report();
+    time_lock_check.check().unwrap();
 
-    for (lock_script, capacity) in genesis_final_records {
+    for record in genesis_final_records {
+        let lock_script = record
+            .lock_script(last_epoch)
+            .unwrap_or_else(|err| panic!("genesis_final.csv row {}: {}", record.row, err));
         spec.genesis.issued_cells.push(IssuedCell {
-            capacity: Capacity::shannons(capacity),
+            capacity: Capacity::shannons(record.capacity),
             lock: lock_script.into(),
         });
+        provenances.push(Provenance::new("genesis_final.csv", format!("row {}", record.row)));
     }
 
     {
+        // The genesis message cell, system cells and dep groups don't depend on issued cells
+        let preview_consensus = spec.build_consensus().unwrap();
+        let reserve = foundation::FoundationReserve::from_consensus(&spec, &preview_consensus).unwrap();
+        println!(">> foundation reserve: {}", reserve);
+        reserve.check(crate::consts::FOUNDATION_RESERVE).unwrap();
+        let consumed_cells = std::iter::once(reserve.genesis_cell)
+            .chain(reserve.system_cells.iter().map(|(_, capacity)| *capacity))
+            .chain(reserve.dep_groups.iter().map(|(_, capacity)| *capacity));
+        for capacity in consumed_cells {
+            category_summary.add(Some(Category::Foundation), None, capacity);
+        }
+        category_summary.add(
+            Some(Category::Foundation),
+            Some(crate::consts::FOUNDATION_RESERVE_LOCK_TIME.to_string()),
+            reserve.reserve,
+        );
+
         let addr = Address::from_input(crate::consts::FOUNDATION_RESERVE_ADDR).unwrap().1;
         let lock_arg: Bytes = build_multisig_lock_arg(
             addr,
             crate::consts::FOUNDATION_RESERVE_LOCK_TIME,
             last_epoch,
-        ).into();
+        )
+        .unwrap()
+        .into();
         let lock_script = packed::Script::new_builder()
             .code_hash(crate::consts::MULTISIG_TYPE_SCRIPT_HASH.pack())
             .hash_type(ScriptHashType::Type.into())
             .args(lock_arg.pack())
             .build();
         spec.genesis.issued_cells.push(IssuedCell {
-            capacity: Capacity::shannons(crate::consts::FOUNDATION_RESERVE),
+            capacity: Capacity::shannons(reserve.reserve),
             lock: lock_script.into(),
         });
+        provenances.push(Provenance::new("foundation", "reserve".to_string()));
     }
     // == Put testnet records into spec
-    for (lock_arg, capacity) in testnet_result.rewards.iter().cloned() {
-        let lock_script = packed::Script::new_builder()
-            .code_hash(consts::SECP_TYPE_SCRIPT_HASH.pack())
-            .hash_type(ScriptHashType::Type.into())
-            .args(lock_arg.pack())
-            .build();
-        spec.genesis.issued_cells.push(IssuedCell {
-            capacity: Capacity::shannons(capacity),
-            lock: lock_script.into(),
-        });
+    let mut unmapped_locks = Vec::new();
+    for (testnet_lock, capacity) in testnet_result.rewards.iter().cloned() {
+        category_summary.add(Some(Category::Testnet), None, capacity);
+        match lock_mapping.map(&testnet_lock) {
+            Ok(lock_script) => {
+                spec.genesis.issued_cells.push(IssuedCell {
+                    capacity: Capacity::shannons(capacity),
+                    lock: lock_script.into(),
+                });
+                provenances.push(Provenance::new("testnet", display_lock(&testnet_lock)));
+            }
+            Err(err) => unmapped_locks.push(format!("{}, capacity: {}", err, capacity)),
+        }
+    }
+    if !unmapped_locks.is_empty() {
+        panic!("Testnet rewards without mainnet lock:\n{}", unmapped_locks.join("\n"));
+    }
+    let category_issues = category_summary.print_report(categories_tagged);
+    if category_issues > 0 {
+        println!("WARN: {} categories not matching their published share", category_issues);
     }
 
-    spec.genesis.timestamp = testnet_result.last_timestamp;
-    spec.genesis.genesis_cell.message = format!("lina {:#x}", testnet_result.last_block_hash);
-    spec.genesis.compact_target = testnet_result.mainnet_difficulty;
-    spec.params.genesis_epoch_length = testnet_result.last_epoch_length;
-    println!(">> timestamp: {}", spec.genesis.timestamp);
-    println!(">> message: {}", spec.genesis.genesis_cell.message);
-    println!(">> compact_target: {:#x}", spec.genesis.compact_target);
-    println!(">> genesis_epoch_length: {:#x}", spec.params.genesis_epoch_length);
-
+    let lint_issues = lint::lint_issued_cells(&spec, &provenances);
+    lint::print_lint_report(&lint_issues);
+    if !lint_issues.is_empty() {
+        println!("WARN: {} issued cells flagged by lint", lint_issues.len());
+    }
+    checks::check_occupied_capacities(&spec).unwrap();
     let consensus = spec.build_consensus().unwrap();
+    let genesis_scripts = checks::GenesisScripts::from_consensus(&spec, &consensus).unwrap();
+    println!(">> sighash type hash: {:#x}", genesis_scripts.sighash_type_hash);
+    println!(">> multisig type hash: {:#x}", genesis_scripts.multisig_type_hash);
+    genesis_scripts.check_consts().unwrap();
+    genesis_scripts.check_issued_cells(&spec).unwrap();
+    println!("==== Burn cells");
+    for proof in burn::check_burn_cells(&spec, &genesis_scripts).unwrap() {
+        println!("  > {}", proof);
+    }
+    let genesis_capacity =
+        genesis_capacity::GenesisCapacity::from_consensus(&spec, &consensus).unwrap();
+    println!(">> genesis capacity: {}", genesis_capacity);
+    let genesis_hash: H256 = consensus.genesis_hash().unpack();
+    spec.genesis.hash = Some(genesis_hash.clone());
 
     // println!(
     //     "==== spec ====: \n{}\n",
     //     toml::to_string_pretty(&spec).unwrap()
     // );
-    let mut file = fs::File::create("final-spec.toml").unwrap();
+    let mut file = fs::File::create(FINAL_SPEC_PATH).unwrap();
     file.write_all(toml::to_string_pretty(&spec).unwrap().as_bytes()).unwrap();
+    drop(file);
+    checks::verify_spec_file(FINAL_SPEC_PATH, &genesis_hash).unwrap();
+    println!(">> verified {} => genesis hash: {:#x}", FINAL_SPEC_PATH, genesis_hash);
 
     let mut total_capacity = 0u64;
     for output in consensus.genesis_block().transactions()[0].outputs().into_iter() {
@@ -152,26 +501,87 @@ fn main() {
 pub struct AddressParser;
 impl AddressParser {
     fn parse(&self, input: &str) -> Result<H160, String> {
-        if let Ok((_network, address)) = Address::from_input(input) {
-            return Ok(address.hash().clone());
-        }
+        self.parse_network(input).map(|(_network, hash)| hash)
+    }
+
+    fn parse_network(&self, input: &str) -> Result<(NetworkType, H160), String> {
+        let err = match Address::from_input(input) {
+            Ok((network, address)) => {
+                let hash = address
+                    .hash()
+                    .ok_or_else(|| format!("Not a sighash address: {}", input))?;
+                return Ok((network, hash));
+            }
+            Err(err) => err,
+        };
 
         let prefix = input.chars().take(3).collect::<String>();
-        let network = NetworkType::from_prefix(prefix.as_str())
-            .ok_or_else(|| format!("Invalid address prefix: {}", prefix))?;
-        let old_address = OldAddress::from_input(network, input)?;
-        Ok(old_address.hash().clone())
+        NetworkType::from_prefix(prefix.as_str())
+            .ok_or_else(|| format!("Invalid address prefix: {}", prefix))
+            .and_then(|network| {
+                OldAddress::from_input(network, input)
+                    .map(|old_address| (network, old_address.hash().clone()))
+            })
+            .map_err(|old_err| format!("{}, old format: {}", err, old_err))
+    }
+
+    // Any lock script, including full format addresses of non-sighash locks
+    fn parse_script(&self, input: &str) -> Result<(NetworkType, packed::Script), String> {
+        match Address::from_input(input) {
+            Ok((network, address)) => Ok((network, address.to_script())),
+            Err(_) => self
+                .parse_network(input)
+                .map(|(network, hash)| (network, sighash_lock_script(hash))),
+        }
     }
 }
 
-pub fn build_multisig_lock_arg(address: Address, datetime_str: &str, last_epoch: u64) -> Vec<u8> {
+pub fn hex_decode(input: &str) -> Result<Vec<u8>, String> {
+    if input.len() % 2 != 0 {
+        return Err("odd hex length".to_string());
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&input[index..index + 2], 16).map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+pub fn sighash_lock_script(hash: H160) -> packed::Script {
+    Address::new_default(hash).lock_script(crate::consts::SECP_TYPE_SCRIPT_HASH.pack())
+}
+
+// Show sighash locks as mainnet address
+pub fn display_lock(lock_script: &packed::Script) -> String {
+    if lock_script.code_hash() == crate::consts::SECP_TYPE_SCRIPT_HASH.pack()
+        && lock_script.hash_type() == ScriptHashType::Type.into()
+    {
+        if let Ok(address) = Address::from_lock_arg(&lock_script.args().raw_data()) {
+            return address.to_string(NetworkType::MainNet);
+        }
+    }
+    lock_script.to_string()
+}
+
+fn parse_lock_datetime(datetime_str: &str) -> Result<DateTime<FixedOffset>, String> {
     let datetime_string = if datetime_str.len() == 10 {
         format!("{}{}", datetime_str, crate::consts::DEFAULT_TIME_SUFFIX)
     } else {
         datetime_str.to_string()
     };
+    DateTime::parse_from_rfc3339(datetime_string.as_str())
+        .map_err(|err| format!("invalid lock date {}: {}", datetime_str, err))
+}
+
+// Unlock epoch of the lock date, the mainnet epoch 0 is the testnet epoch
+// `last_epoch - 89` counted from `SINCE_BEGIN`, epochs are 4 hours.
+pub fn unlock_epoch(
+    datetime_str: &str,
+    last_epoch: u64,
+) -> Result<EpochNumberWithFraction, String> {
     let since_begin = DateTime::parse_from_rfc3339(crate::consts::SINCE_BEGIN).unwrap();
-    let datetime = DateTime::parse_from_rfc3339(datetime_string.as_str()).unwrap();
+    let datetime = parse_lock_datetime(datetime_str)?;
     let seconds = if datetime <= since_begin {
         0
     } else {
@@ -179,20 +589,38 @@ pub fn build_multisig_lock_arg(address: Address, datetime_str: &str, last_epoch:
     };
 
     // number
-    let se = seconds / 14400 + 89 - last_epoch;
+    let se = (seconds / 14400 + 89).checked_sub(last_epoch).ok_or_else(|| {
+        format!(
+            "unlock epoch of {} underflows with last epoch {}",
+            datetime_str, last_epoch
+        )
+    })?;
     // index
     let sn = (seconds % 14400) * 1800 / 14400;
     // length
     let sd = 1800;
-    let sf = 32;
-    let epoch = EpochNumberWithFraction::new(se, sn, sd);
+    Ok(EpochNumberWithFraction::new(se, sn, sd))
+}
+
+pub fn build_multisig_lock_arg(
+    address: Address,
+    datetime_str: &str,
+    last_epoch: u64,
+) -> Result<Vec<u8>, String> {
+    let hash = address.hash().ok_or_else(|| {
+        format!(
+            "time locked cells need a sighash address: {}",
+            address.to_string(NetworkType::MainNet)
+        )
+    })?;
+    let epoch = unlock_epoch(datetime_str, last_epoch)?;
     let since = 0x2000_0000_0000_0000 | epoch.full_value();
 
     let mut data = {
         let mut buf = vec![0, 0, 1, 1];
-        buf.extend_from_slice(address.hash().as_bytes());
+        buf.extend_from_slice(hash.as_bytes());
         blake2b_256(&buf)[..20].to_vec()
     };
     data.extend(since.to_le_bytes().iter());
-    data
+    Ok(data)
 }
@@ -0,0 +1,80 @@
+use ckb_types::H160;
+
+use crate::basic::NetworkType;
+use crate::AddressParser;
+
+pub struct NetworkViolation {
+    pub source: String,
+    pub address: String,
+    pub expected: NetworkType,
+    pub actual: NetworkType,
+}
+
+// Every address source declares the network it expects, mainnet recipients
+// must not be given testnet addresses and vice versa.
+pub struct NetworkCheck {
+    // Fail instead of warn on violations
+    pub strict: bool,
+    pub violations: Vec<NetworkViolation>,
+}
+
+impl NetworkCheck {
+    pub fn new(strict: bool) -> NetworkCheck {
+        NetworkCheck {
+            strict,
+            violations: Vec::new(),
+        }
+    }
+
+    // Errors only when the address is invalid, violations are collected
+    pub fn parse(
+        &mut self,
+        source: &str,
+        input: &str,
+        expected: NetworkType,
+    ) -> Result<H160, String> {
+        let (actual, hash) = AddressParser.parse_network(input)?;
+        self.expect(source, input, expected, actual);
+        Ok(hash)
+    }
+
+    pub fn expect(
+        &mut self,
+        source: &str,
+        input: &str,
+        expected: NetworkType,
+        actual: NetworkType,
+    ) {
+        if actual != expected {
+            log::warn!("{}: {} is a {} address, expected {}", source, input, actual, expected);
+            self.violations.push(NetworkViolation {
+                source: source.to_string(),
+                address: input.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+
+    pub fn print_report(&self) {
+        println!(
+            "==== Address network violations: {}, strict: {}",
+            self.violations.len(),
+            self.strict
+        );
+        for violation in &self.violations {
+            println!(
+                "  > {}: {}, expected: {}, actual: {}",
+                violation.source, violation.address, violation.expected, violation.actual
+            );
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.strict && !self.violations.is_empty() {
+            Err(format!("{} addresses of unexpected network", self.violations.len()))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,12 +1,22 @@
 use crate::consts::ONE_CKB;
-use crate::AddressParser;
 use ckb_hash::new_blake2b;
-use ckb_types::{H160, H256, bytes::Bytes};
+use ckb_types::{packed, H256};
 use std::collections::HashMap;
-use crate::basic::{Address, NetworkType};
-use crate::last_round;
+use crate::basic::Address;
+use crate::{display_lock, sighash_lock_script};
+use crate::adjustments::{apply_adjustments, print_adjustments_report, Adjustment};
+use crate::apportion::Apportionment;
+use crate::exclusions::Exclusions;
+use crate::last_round::{self, DifficultyPolicy, DroppedRewards, Weighting};
+use crate::network_check::NetworkCheck;
+use crate::recovery::{is_missing_address, AddressRecovery};
+use crate::basic::NetworkType;
+use crate::redirects::{apply_redirects, print_redirects_report, Redirect};
 
-pub fn read_round1_rewards() -> Vec<(H160, u64)> {
+// Round data are recorded with testnet addresses
+pub const ROUND_NETWORK: NetworkType = NetworkType::TestNet;
+
+pub fn read_round1_rewards(network_check: &mut NetworkCheck) -> Vec<(packed::Script, u64)> {
     let mut rdr = csv::Reader::from_reader(crate::data::DATA_ROUND1.as_bytes());
     let mut results = Vec::new();
     for record in rdr.records() {
@@ -16,11 +26,11 @@ pub fn read_round1_rewards() -> Vec<(H160, u64)> {
             log::warn!("empty address lock_hash={}", record.get(1).unwrap());
             continue;
         }
-        match AddressParser.parse(address_str) {
+        match network_check.parse("round1", address_str, ROUND_NETWORK) {
             Ok(hash) => {
                 let capacity: u64 = record.get(1).unwrap().parse::<u64>().unwrap() * ONE_CKB;
                 log::debug!("{:#} => {}", hash, capacity);
-                results.push((hash, capacity));
+                results.push((sighash_lock_script(hash), capacity));
             }
             Err(err) => {
                 log::warn!("invalid address {}, error: {}", address_str, err);
@@ -30,45 +40,64 @@ pub fn read_round1_rewards() -> Vec<(H160, u64)> {
     results
 }
 
-pub fn read_round2_rewards() -> Vec<(H160, u64)> {
+pub fn read_round2_rewards(
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
     read_epoch_lucky_rewords(
+        "2",
         crate::data::DATA_ROUND2_MINER,
+        2,
         crate::data::DATA_ROUND2_EPOCH,
         crate::consts::EPOCH_REWARD2,
+        recovery,
+        network_check,
     )
 }
 
-pub fn read_round3_rewards() -> Vec<(H160, u64)> {
+pub fn read_round3_rewards(
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
     read_epoch_lucky_rewords(
+        "3",
         crate::data::DATA_ROUND3_MINER,
+        1,
         crate::data::DATA_ROUND3_EPOCH,
         crate::consts::EPOCH_REWARD3,
+        recovery,
+        network_check,
     )
 }
 
 pub fn read_epoch_lucky_rewords(
+    round_name: &str,
     miner_data: &str,
+    miner_lock_hash_column: usize,
     epoch_data: &str,
     epoch_reward: u64,
-) -> Vec<(H160, u64)> {
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
     let mut results = Vec::new();
     let mut rdr_miner = csv::Reader::from_reader(miner_data.as_bytes());
     for record in rdr_miner.records() {
         let record = record.unwrap();
         let address_str = record.get(0).unwrap();
-        if address_str.is_empty() {
-            log::warn!(
-                "empty address info: {}, {}",
-                record.get(1).unwrap(),
-                record.get(2).unwrap()
-            );
+        let capacity: u64 = record.get(3).unwrap().parse::<u64>().unwrap() * ONE_CKB;
+        if is_missing_address(address_str) {
+            let lock_hash = record.get(miner_lock_hash_column).unwrap();
+            let round = format!("{}-miner", round_name);
+            if let Some(hash) = recovery.resolve(&round, lock_hash, capacity) {
+                results.push((sighash_lock_script(hash), capacity));
+            }
             continue;
         }
-        match AddressParser.parse(address_str) {
+        let source = format!("round{}-miner", round_name);
+        match network_check.parse(&source, address_str, ROUND_NETWORK) {
             Ok(hash) => {
-                let capacity: u64 = record.get(3).unwrap().parse::<u64>().unwrap() * ONE_CKB;
                 log::debug!("miner {:#} => {}", hash, capacity);
-                results.push((hash, capacity));
+                results.push((sighash_lock_script(hash), capacity));
             }
             Err(err) => {
                 log::warn!("invalid address {}, error: {}", address_str, err);
@@ -80,14 +109,19 @@ pub fn read_epoch_lucky_rewords(
     for record in rdr_epoch.records() {
         let record = record.unwrap();
         let address_str = record.get(1).unwrap();
-        if address_str.is_empty() {
-            log::warn!("empty address lock-hash: {}", record.get(2).unwrap());
+        if is_missing_address(address_str) {
+            let lock_hash = record.get(2).unwrap();
+            let round = format!("{}-epoch", round_name);
+            if let Some(hash) = recovery.resolve(&round, lock_hash, epoch_reward) {
+                results.push((sighash_lock_script(hash), epoch_reward));
+            }
             continue;
         }
-        match AddressParser.parse(address_str) {
+        let source = format!("round{}-epoch", round_name);
+        match network_check.parse(&source, address_str, ROUND_NETWORK) {
             Ok(hash) => {
                 log::debug!("epoch {:#} => {}", hash, epoch_reward);
-                results.push((hash, epoch_reward));
+                results.push((sighash_lock_script(hash), epoch_reward));
             }
             Err(err) => {
                 log::warn!("invalid address {}, error: {}", address_str, err);
@@ -97,37 +131,51 @@ pub fn read_epoch_lucky_rewords(
     results
 }
 
-pub fn read_round4_rewards() -> Vec<(H160, u64)> {
-    read_normal_rewards(crate::data::DATA_ROUND4)
+pub fn read_round4_rewards(
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
+    read_normal_rewards("4", crate::data::DATA_ROUND4, recovery, network_check)
 }
 
-pub fn read_round5_stage1_rewards() -> Vec<(H160, u64)> {
-    read_normal_rewards(crate::data::DATA_ROUND5_STAGE1)
+pub fn read_round5_stage1_rewards(
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
+    read_normal_rewards("5-stage1", crate::data::DATA_ROUND5_STAGE1, recovery, network_check)
 }
 
-pub fn read_round5_stage2_rewards() -> Vec<(H160, u64)> {
-    read_normal_rewards(crate::data::DATA_ROUND5_STAGE2)
+pub fn read_round5_stage2_rewards(
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
+    read_normal_rewards("5-stage2", crate::data::DATA_ROUND5_STAGE2, recovery, network_check)
 }
 
-pub fn read_normal_rewards(data: &str) -> Vec<(H160, u64)> {
+pub fn read_normal_rewards(
+    round_name: &str,
+    data: &str,
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(packed::Script, u64)> {
     let mut results = Vec::new();
     let mut rdr = csv::Reader::from_reader(data.as_bytes());
     for record in rdr.records() {
         let record = record.unwrap();
         let address_str = record.get(0).unwrap();
-        if address_str.is_empty() {
-            log::warn!(
-                "empty address info: {}, {}",
-                record.get(1).unwrap(),
-                record.get(2).unwrap()
-            );
+        let capacity: u64 = record.get(3).unwrap().parse::<u64>().unwrap() * ONE_CKB;
+        if is_missing_address(address_str) {
+            let lock_hash = record.get(2).unwrap();
+            if let Some(hash) = recovery.resolve(round_name, lock_hash, capacity) {
+                results.push((sighash_lock_script(hash), capacity));
+            }
             continue;
         }
-        match AddressParser.parse(address_str) {
+        let source = format!("round{}", round_name);
+        match network_check.parse(&source, address_str, ROUND_NETWORK) {
             Ok(hash) => {
-                let capacity: u64 = record.get(3).unwrap().parse::<u64>().unwrap() * ONE_CKB;
                 log::debug!("{:#} => {}", hash, capacity);
-                results.push((hash, capacity));
+                results.push((sighash_lock_script(hash), capacity));
             }
             Err(err) => {
                 log::warn!("invalid address {}, error: {}", address_str, err);
@@ -138,7 +186,8 @@ pub fn read_normal_rewards(data: &str) -> Vec<(H160, u64)> {
 }
 
 pub struct TestnetResut {
-    pub rewards: Vec<(Bytes, u64)>,
+    // Testnet lock scripts, map them before putting into mainnet spec
+    pub rewards: Vec<(packed::Script, u64)>,
     pub total_capacity: u64,
     pub last_block_hash: H256,
     pub last_timestamp: u64,
@@ -147,57 +196,106 @@ pub struct TestnetResut {
     pub mainnet_difficulty: u32,
 }
 
-pub fn all_rewards(url: &str, last_epoch: u64, confirmations: u16) -> TestnetResut {
-    let current_testnet_result = last_round::read_last_round(url, last_epoch, confirmations);
+// Rewards of the recorded rounds, read before scanning testnet so their
+// addresses are checked first
+pub fn read_previous_rounds(
+    recovery: &mut AddressRecovery,
+    network_check: &mut NetworkCheck,
+) -> Vec<(&'static str, Vec<(packed::Script, u64)>)> {
+    vec![
+        ("1", read_round1_rewards(network_check)),
+        ("2", read_round2_rewards(recovery, network_check)),
+        ("3", read_round3_rewards(recovery, network_check)),
+        ("4", read_round4_rewards(recovery, network_check)),
+        ("5-stage1", read_round5_stage1_rewards(recovery, network_check)),
+        ("5-stage2", read_round5_stage2_rewards(recovery, network_check)),
+    ]
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn all_rewards(
+    url: &str,
+    last_epoch: u64,
+    confirmations: u16,
+    difficulty_policy: &DifficultyPolicy,
+    difficulty_candidates: &[DifficultyPolicy],
+    apportionment: Apportionment,
+    weighting: Weighting,
+    min_reward: u64,
+    dropped_rewards: DroppedRewards,
+    exclusions: &mut Exclusions,
+    count_excluded_base_reward: bool,
+    previous_rounds: Vec<(&'static str, Vec<(packed::Script, u64)>)>,
+    recovery: &AddressRecovery,
+    adjustments: &[Adjustment],
+    redirects: &[Redirect],
+) -> TestnetResut {
+    let current_testnet_result = last_round::read_last_round(
+        url,
+        last_epoch,
+        confirmations,
+        difficulty_policy,
+        difficulty_candidates,
+        weighting,
+        min_reward,
+        dropped_rewards,
+        exclusions,
+        count_excluded_base_reward,
+    );
+    last_round::print_miner_stats_report(&current_testnet_result);
+    last_round::print_dropped_report(&current_testnet_result);
+    let last_round_shares = current_testnet_result.real_rewards(apportionment);
+    last_round::print_apportionment_report(&last_round_shares, apportionment);
 
-    let mut result: HashMap<H160, u64> = HashMap::default();
+    let mut result: HashMap<packed::Script, u64> = HashMap::default();
 
-    for (round_name, round_rewards) in vec![
-        ("1", read_round1_rewards()),
-        ("2", read_round2_rewards()),
-        ("3", read_round3_rewards()),
-        ("4", read_round4_rewards()),
-        ("5-stage1", read_round5_stage1_rewards()),
-        ("5-stage2", read_round5_stage2_rewards()),
-        ("last-round", current_testnet_result.real_rewards()),
-    ]
-    .into_iter()
+    let last_round_rewards: Vec<(packed::Script, u64)> = last_round_shares
+        .into_iter()
+        .map(|share| (share.key, share.amount))
+        .collect();
+    for (round_name, round_rewards) in previous_rounds
+        .into_iter()
+        .chain(std::iter::once(("last-round", last_round_rewards)))
     {
+        let round_rewards = exclusions.filter(round_name, round_rewards);
         let mut total_capacity = 0;
         let count = round_rewards.len();
-        for (lock_hash, capacity) in round_rewards {
-            log::info!("round{}: {:#} => {}", round_name, lock_hash, capacity);
+        for (lock_script, capacity) in round_rewards {
+            log::info!("round{}: {} => {}", round_name, display_lock(&lock_script), capacity);
             total_capacity += capacity;
-            *result.entry(lock_hash).or_default() += capacity;
+            *result.entry(lock_script).or_default() += capacity;
         }
         println!(
             "==== Round {}, count: {}, total_capacity: {}\n",
             round_name, count, total_capacity
         );
     }
+    recovery.print_report();
+    exclusions.print_report();
+    let applied = apply_adjustments(adjustments, &mut result).unwrap();
+    print_adjustments_report(&applied);
+    let redirected = apply_redirects(redirects, &mut result);
+    print_redirects_report(&redirected);
 
-    let mut rewards: Vec<(Bytes, u64)> = result
-        .into_iter()
-        .map(|(lock_arg, capacity)| (Bytes::from(lock_arg.as_bytes()), capacity))
-        .collect();
-    rewards.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut rewards: Vec<(packed::Script, u64)> = result.into_iter().collect();
+    rewards.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
 
     let mut total_capacity = 0;
-    for (lock_hash, capacity) in &rewards {
-        let address_string = Address::from_lock_arg(&lock_hash)
-            .unwrap()
-            .to_string(NetworkType::MainNet);
-        println!("previous: {} => {}", address_string, capacity);
+    for (lock_script, capacity) in &rewards {
+        println!("previous: {} => {}", display_lock(lock_script), capacity);
         total_capacity += capacity;
     }
-    let testnet_foundation_lock_arg = Address::from_input(crate::consts::TESTNET_FOUNDATION_ADDR)
-        .unwrap()
-        .1
-        .hash()
-        .as_bytes()
-        .into();
-    let testnet_foundation_capacity = crate::consts::INIT_TESTNET - total_capacity;
-    rewards.push((testnet_foundation_lock_arg, testnet_foundation_capacity));
+    let testnet_foundation_lock = sighash_lock_script(
+        Address::from_input(crate::consts::TESTNET_FOUNDATION_ADDR)
+            .unwrap()
+            .1
+            .hash()
+            .unwrap(),
+    );
+    let testnet_foundation_capacity = crate::consts::INIT_TESTNET
+        .checked_sub(total_capacity)
+        .unwrap_or_else(|| panic!("testnet rewards {} exceed INIT_TESTNET", total_capacity));
+    rewards.push((testnet_foundation_lock, testnet_foundation_capacity));
     println!("count: {}", rewards.len());
     println!("total-capacity: {}", total_capacity);
     println!(" rest-capacity: {}", testnet_foundation_capacity);
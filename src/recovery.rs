@@ -0,0 +1,213 @@
+use ckb_types::{H160, H256};
+
+use std::collections::HashMap;
+
+use crate::round_checks::{lock_hash_matches, round_scheme, LockHashScheme};
+
+// Lock hash to lock args index, loaded from a CSV file with header
+// `lock_hash,args[,source]`, `source` tells where the pair comes from
+// (eg. the block number of a recorded chain snapshot).
+pub struct LockHashIndex {
+    path: String,
+    entries: Vec<IndexEntry>,
+}
+
+struct IndexEntry {
+    lock_hash: H256,
+    args: H160,
+    source: String,
+    line: u64,
+}
+
+impl LockHashIndex {
+    pub fn load(path: &str) -> Result<LockHashIndex, String> {
+        let mut rdr = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+        let mut entries = Vec::new();
+        let mut lock_hashes = HashMap::new();
+        for record in rdr.records() {
+            let record = record.map_err(|err| err.to_string())?;
+            let line = record.position().map(|position| position.line()).unwrap_or_default();
+            let lock_hash_str = record.get(0).unwrap_or_default().trim();
+            let lock_hash = crate::hex_decode(lock_hash_str.trim_start_matches("0x"))
+                .and_then(|bytes| H256::from_slice(&bytes).map_err(|err| err.to_string()))
+                .map_err(|err| {
+                    format!("{}:{} invalid lock hash {}: {}", path, line, lock_hash_str, err)
+                })?;
+            let args_str = record.get(1).unwrap_or_default().trim();
+            let args_bytes = crate::hex_decode(args_str.trim_start_matches("0x"))
+                .map_err(|err| format!("{}:{} invalid args {}: {}", path, line, args_str, err))?;
+            let args = H160::from_slice(&args_bytes)
+                .map_err(|err| format!("{}:{} invalid args {}: {}", path, line, args_str, err))?;
+            if let Some(previous_line) = lock_hashes.insert(lock_hash.clone(), line) {
+                return Err(format!(
+                    "{}:{} duplicated lock hash {:#x}, first seen at line {}",
+                    path, line, lock_hash, previous_line
+                ));
+            }
+            entries.push(IndexEntry {
+                lock_hash,
+                args,
+                source: record.get(2).unwrap_or_default().to_string(),
+                line,
+            });
+        }
+        Ok(LockHashIndex {
+            path: path.to_string(),
+            entries,
+        })
+    }
+
+    // The recorded lock hash may be truncated, it must match exactly one entry
+    fn lookup(&self, recorded: &str) -> Result<&IndexEntry, String> {
+        let mut matched = self
+            .entries
+            .iter()
+            .filter(|entry| lock_hash_matches(recorded, &entry.lock_hash));
+        match (matched.next(), matched.next()) {
+            (Some(entry), None) => Ok(entry),
+            (None, _) => Err("not in index".to_string()),
+            (Some(_), Some(_)) => Err("ambiguous in index".to_string()),
+        }
+    }
+}
+
+pub struct RecoveredRow {
+    pub round: String,
+    pub lock_hash: String,
+    pub args: H160,
+    pub capacity: u64,
+    // Where the args come from: index file, line and source column
+    pub provenance: String,
+}
+
+// Rows of round data without address, resolved by lock hash when an index is given
+#[derive(Default)]
+pub struct AddressRecovery {
+    index: Option<LockHashIndex>,
+    pub recovered: Vec<RecoveredRow>,
+    pub unresolved: Vec<(String, String, u64, String)>,
+}
+
+impl AddressRecovery {
+    pub fn new(index: Option<LockHashIndex>) -> AddressRecovery {
+        AddressRecovery {
+            index,
+            ..Default::default()
+        }
+    }
+
+    // Args and provenance the lock hash resolves to, the args must reproduce
+    // the lock hash under the round's scheme. Doesn't record the row.
+    pub fn lookup(
+        &self,
+        lock_hash: &str,
+        scheme: &LockHashScheme,
+    ) -> Result<(H160, String), String> {
+        let index = self.index.as_ref().ok_or_else(|| "no lock hash index".to_string())?;
+        let entry = index.lookup(lock_hash)?;
+        let provenance = format!("{}:{} {}", index.path, entry.line, entry.source);
+        let computed = scheme.lock_hash(&entry.args);
+        if computed != entry.lock_hash {
+            return Err(format!(
+                "args {:#} from {} compute lock hash {:#x}",
+                entry.args, provenance, computed
+            ));
+        }
+        Ok((entry.args.clone(), provenance))
+    }
+
+    pub fn resolve(&mut self, round: &str, lock_hash: &str, capacity: u64) -> Option<H160> {
+        let source = format!("round{}", round);
+        let result = round_scheme(&source)
+            .ok_or_else(|| format!("no lock hash scheme for {}", source))
+            .and_then(|scheme| self.lookup(lock_hash, &scheme));
+        match result {
+            Ok((args, provenance)) => {
+                log::info!("recovered {} lock_hash={} => {:#}", round, lock_hash, args);
+                self.recovered.push(RecoveredRow {
+                    round: round.to_string(),
+                    lock_hash: lock_hash.to_string(),
+                    args: args.clone(),
+                    capacity,
+                    provenance,
+                });
+                Some(args)
+            }
+            Err(reason) => {
+                log::warn!("empty address {} lock_hash={}: {}", round, lock_hash, reason);
+                self.unresolved.push((
+                    round.to_string(),
+                    lock_hash.to_string(),
+                    capacity,
+                    reason,
+                ));
+                None
+            }
+        }
+    }
+
+    pub fn print_report(&self) {
+        println!(
+            "==== Rows without address, recovered: {}, unresolved: {}",
+            self.recovered.len(),
+            self.unresolved.len()
+        );
+        for row in &self.recovered {
+            println!(
+                "  > recovered round{}: lock_hash: {}, args: {:#}, capacity: {}, from: {}",
+                row.round, row.lock_hash, row.args, row.capacity, row.provenance
+            );
+        }
+        for (round, lock_hash, capacity, reason) in &self.unresolved {
+            println!(
+                "  > unresolved round{}: lock_hash: {}, capacity: {}, {}",
+                round, lock_hash, capacity, reason
+            );
+        }
+    }
+}
+
+// `NULL` is used by some round data for missing address
+pub fn is_missing_address(address: &str) -> bool {
+    address.is_empty() || address == "NULL"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::h256;
+
+    #[test]
+    fn test_resolve_checks_lock_hash() {
+        let args = crate::AddressParser
+            .parse("ckt1q9gry5zgvkprd780vqhlspq8gq5j0engkvwaqj350nxwq8")
+            .unwrap();
+        let entry = |lock_hash: H256, line: u64| IndexEntry {
+            lock_hash,
+            args: args.clone(),
+            source: String::new(),
+            line,
+        };
+        let index = LockHashIndex {
+            path: "index.csv".to_string(),
+            entries: vec![
+                entry(
+                    h256!("0xc9a17778b99b59067af358390da31f4de29ea669e93d19b199948ee4a1f51c40"),
+                    2,
+                ),
+                entry(
+                    h256!("0x1111111111111111111111111111111111111111111111111111111111111111"),
+                    3,
+                ),
+            ],
+        };
+        let mut recovery = AddressRecovery::new(Some(index));
+        assert_eq!(recovery.resolve("2-miner", "0xc9a17778b99b...a1f51c40", 1), Some(args));
+        // Args of a wrong index entry don't reproduce the lock hash
+        assert_eq!(recovery.resolve("2-miner", "0x1111111111111111...11111111", 1), None);
+        // Round 3 lock hashes are computed by another scheme
+        assert_eq!(recovery.resolve("3-miner", "0xc9a17778b99b...a1f51c40", 1), None);
+        assert_eq!(recovery.recovered.len(), 1);
+        assert_eq!(recovery.unresolved.len(), 2);
+    }
+}
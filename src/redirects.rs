@@ -0,0 +1,164 @@
+use ckb_crypto::secp::Signature;
+use ckb_hash::blake2b_256;
+use ckb_types::{packed, H160, H256};
+
+use std::collections::HashMap;
+
+use crate::basic::{Address, NetworkType};
+use crate::network_check::NetworkCheck;
+use crate::previous_rounds::ROUND_NETWORK;
+use crate::{sighash_lock_script, AddressParser};
+
+// A participant asks to move the testnet reward of `from` to the mainnet
+// address `to`, the request is signed by the key of `from` over `message_hash()`.
+pub struct Redirect {
+    pub line: u64,
+    pub from: String,
+    pub to: String,
+    pub signature: String,
+}
+
+impl Redirect {
+    pub fn message(&self) -> String {
+        format!("Redirect testnet reward of {} to {}", self.from, self.to)
+    }
+
+    // The signature is a 65 bytes recoverable secp256k1 signature of this hash
+    pub fn message_hash(&self) -> H256 {
+        H256::from_slice(&blake2b_256(self.message().as_bytes())).unwrap()
+    }
+
+    // Returns the `from` lock args and the `to` lock script when the signature is valid
+    pub fn verify(&self) -> Result<(H160, packed::Script), String> {
+        let from = AddressParser.parse(&self.from)?;
+        let (_, to) = AddressParser.parse_script(&self.to)?;
+        if self.signature.is_empty() {
+            return Err("unsigned".to_string());
+        }
+        let signature_bytes = crate::hex_decode(self.signature.trim_start_matches("0x"))?;
+        let signature = Signature::from_slice(&signature_bytes)
+            .map_err(|err| format!("invalid signature: {}", err))?;
+        let pubkey = signature
+            .recover(&self.message_hash())
+            .map_err(|err| format!("recover pubkey failed: {}", err))?;
+        let pubkey = secp256k1::PublicKey::from_slice(&pubkey.serialize())
+            .map_err(|err| err.to_string())?;
+        let signer = Address::from_pubkey(&pubkey)?;
+        if signer.hash().as_ref() != Some(&from) {
+            return Err(format!(
+                "signed by {}, expected {:#}",
+                signer.to_string(NetworkType::MainNet),
+                from
+            ));
+        }
+        Ok((from, to))
+    }
+}
+
+// `from` is a rewarded testnet address, `to` a mainnet address. Invalid
+// addresses are left to `verify`.
+pub fn check_networks(redirects: &[Redirect], network_check: &mut NetworkCheck) {
+    for redirect in redirects {
+        let source = format!("redirects line {}", redirect.line);
+        if let Ok((network, _)) = AddressParser.parse_network(&redirect.from) {
+            network_check.expect(&source, &redirect.from, ROUND_NETWORK, network);
+        }
+        if let Ok((network, _)) = AddressParser.parse_script(&redirect.to) {
+            network_check.expect(&source, &redirect.to, NetworkType::MainNet, network);
+        }
+    }
+}
+
+// Load redirects from a CSV file with header `from,to,signature`
+pub fn load_redirects(path: &str) -> Result<Vec<Redirect>, String> {
+    let mut rdr = csv::Reader::from_path(path).map_err(|err| err.to_string())?;
+    let mut redirects = Vec::new();
+    for record in rdr.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|position| position.line()).unwrap_or_default();
+        redirects.push(Redirect {
+            line,
+            from: record.get(0).unwrap_or_default().trim().to_string(),
+            to: record.get(1).unwrap_or_default().trim().to_string(),
+            signature: record.get(2).unwrap_or_default().trim().to_string(),
+        });
+    }
+    Ok(redirects)
+}
+
+// Move the whole reward of verified requests, the rejected ones are returned with reasons
+pub fn apply_redirects<'a>(
+    redirects: &'a [Redirect],
+    rewards: &mut HashMap<packed::Script, u64>,
+) -> Vec<(&'a Redirect, Result<u64, String>)> {
+    let mut results = Vec::new();
+    for redirect in redirects {
+        let result = redirect.verify().and_then(|(from, to)| {
+            let capacity = rewards
+                .remove(&sighash_lock_script(from))
+                .ok_or_else(|| "no reward to redirect".to_string())?;
+            *rewards.entry(to).or_default() += capacity;
+            Ok(capacity)
+        });
+        results.push((redirect, result));
+    }
+    results
+}
+
+pub fn print_redirects_report(results: &[(&Redirect, Result<u64, String>)]) {
+    println!("==== Address redirects: {}", results.len());
+    for (redirect, result) in results {
+        match result {
+            Ok(capacity) => println!(
+                "  > line {}: {} => {}, capacity: {}",
+                redirect.line, redirect.from, redirect.to, capacity
+            ),
+            Err(err) => println!(
+                "  > REJECTED line {}: {} => {}, {}, message hash: {:#x}",
+                redirect.line,
+                redirect.from,
+                redirect.to,
+                err,
+                redirect.message_hash()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_crypto::secp::Privkey;
+
+    #[test]
+    fn test_verify() {
+        let privkey = Privkey::from_slice(&[1u8; 32]);
+        let pubkey = secp256k1::PublicKey::from_slice(&privkey.pubkey().unwrap().serialize())
+            .unwrap();
+        let from = Address::from_pubkey(&pubkey).unwrap();
+        let mut redirect = Redirect {
+            line: 2,
+            from: from.to_string(NetworkType::TestNet),
+            to: crate::consts::FOUNDATION_RESERVE_ADDR.to_string(),
+            signature: String::new(),
+        };
+        assert_eq!(redirect.verify().unwrap_err(), "unsigned");
+
+        let signature = privkey.sign_recoverable(&redirect.message_hash()).unwrap();
+        redirect.signature = signature
+            .serialize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let (from_hash, to) = redirect.verify().unwrap();
+        assert_eq!(Some(from_hash), from.hash());
+        assert_eq!(
+            to,
+            Address::from_input(crate::consts::FOUNDATION_RESERVE_ADDR).unwrap().1.to_script()
+        );
+
+        // The signature doesn't cover another `to`
+        redirect.to = crate::consts::TESTNET_FOUNDATION_ADDR.to_string();
+        assert!(redirect.verify().is_err());
+    }
+}
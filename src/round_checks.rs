@@ -0,0 +1,584 @@
+use ckb_hash::blake2b_256;
+use ckb_types::{core::ScriptHashType, h256, prelude::*, H160, H256};
+
+use crate::basic::{Address, NetworkType, OldAddress};
+use crate::recovery::{is_missing_address, AddressRecovery};
+use crate::AddressParser;
+
+// How the `lock_hash` column of a round was computed. The testnet changed its
+// system scripts and `Script` serialization between rounds, the code hashes
+// below are the ones reproducing the recorded lock hashes.
+#[derive(Clone, Debug)]
+pub enum LockHashScheme {
+    // Old P2PH address: blake2b(code_hash | args)
+    P2ph(H256),
+    // blake2b(code_hash | hash_type | args)
+    Legacy(H256, ScriptHashType),
+    // Molecule `Script` with `args` as a vector of bytes
+    ArgsVec(H256, ScriptHashType),
+    // Current molecule `Script`
+    Current(H256, ScriptHashType),
+}
+
+impl LockHashScheme {
+    pub fn lock_hash(&self, address: &H160) -> H256 {
+        let args = address.as_bytes();
+        match self {
+            LockHashScheme::P2ph(code_hash) => {
+                let mut data = code_hash.as_bytes().to_vec();
+                data.extend_from_slice(args);
+                H256::from_slice(&blake2b_256(&data)).unwrap()
+            }
+            LockHashScheme::Legacy(code_hash, hash_type) => {
+                let mut data = code_hash.as_bytes().to_vec();
+                data.push(*hash_type as u8);
+                data.extend_from_slice(args);
+                H256::from_slice(&blake2b_256(&data)).unwrap()
+            }
+            LockHashScheme::ArgsVec(code_hash, hash_type) => {
+                let args = molecule_bytes(args);
+                let args_vec = molecule_table(&[&args]);
+                let script =
+                    molecule_table(&[code_hash.as_bytes(), &[*hash_type as u8], &args_vec]);
+                H256::from_slice(&blake2b_256(&script)).unwrap()
+            }
+            LockHashScheme::Current(code_hash, ScriptHashType::Type) => {
+                Address::new_default(address.clone())
+                    .lock_script(code_hash.pack())
+                    .calc_script_hash()
+                    .unpack()
+            }
+            LockHashScheme::Current(code_hash, ScriptHashType::Data) => {
+                OldAddress::new_default(address.clone())
+                    .lock_script(code_hash.clone())
+                    .calc_script_hash()
+                    .unpack()
+            }
+        }
+    }
+}
+
+// Table and dynvec share the same layout: full size, offsets, then items
+fn molecule_table(fields: &[&[u8]]) -> Vec<u8> {
+    let header_size = 4 + 4 * fields.len();
+    let total_size = header_size + fields.iter().map(|field| field.len()).sum::<usize>();
+    let mut data = Vec::with_capacity(total_size);
+    data.extend_from_slice(&(total_size as u32).to_le_bytes());
+    let mut offset = header_size;
+    for field in fields {
+        data.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    for field in fields {
+        data.extend_from_slice(field);
+    }
+    data
+}
+
+fn molecule_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut data = (bytes.len() as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(bytes);
+    data
+}
+
+pub struct RoundSource {
+    pub name: &'static str,
+    pub data: &'static str,
+    pub address_column: usize,
+    pub lock_hash_column: usize,
+    pub scheme: LockHashScheme,
+}
+
+// Round 1 data has no lock hash column
+pub fn round_sources() -> Vec<RoundSource> {
+    let round2 = LockHashScheme::P2ph(h256!(
+        "0x94334bdda40b69bae067d84937aa6bbccf8acd0df6626d4b9ac70d4612a11933"
+    ));
+    let round3 = LockHashScheme::Legacy(
+        h256!("0x54811ce986d5c3e57eaafab22cdd080e32209e39590e204a99b32935f835a13c"),
+        ScriptHashType::Data,
+    );
+    let round4 = LockHashScheme::ArgsVec(
+        h256!("0x1892ea40d82b53c678ff88312450bbb17e164d7a3e0a90941aa58839f56f8df2"),
+        ScriptHashType::Type,
+    );
+    let round5 = LockHashScheme::Current(crate::consts::SECP_TYPE_SCRIPT_HASH, ScriptHashType::Type);
+    vec![
+        RoundSource {
+            name: "round2-miner",
+            data: crate::data::DATA_ROUND2_MINER,
+            address_column: 0,
+            lock_hash_column: 2,
+            scheme: round2.clone(),
+        },
+        RoundSource {
+            name: "round2-epoch",
+            data: crate::data::DATA_ROUND2_EPOCH,
+            address_column: 1,
+            lock_hash_column: 2,
+            scheme: round2,
+        },
+        RoundSource {
+            name: "round3-miner",
+            data: crate::data::DATA_ROUND3_MINER,
+            address_column: 0,
+            lock_hash_column: 1,
+            scheme: round3.clone(),
+        },
+        RoundSource {
+            name: "round3-epoch",
+            data: crate::data::DATA_ROUND3_EPOCH,
+            address_column: 1,
+            lock_hash_column: 2,
+            scheme: round3,
+        },
+        RoundSource {
+            name: "round4",
+            data: crate::data::DATA_ROUND4,
+            address_column: 0,
+            lock_hash_column: 2,
+            scheme: round4,
+        },
+        RoundSource {
+            name: "round5-stage1",
+            data: crate::data::DATA_ROUND5_STAGE1,
+            address_column: 0,
+            lock_hash_column: 2,
+            scheme: round5.clone(),
+        },
+        RoundSource {
+            name: "round5-stage2",
+            data: crate::data::DATA_ROUND5_STAGE2,
+            address_column: 0,
+            lock_hash_column: 2,
+            scheme: round5,
+        },
+    ]
+}
+
+pub fn round_scheme(name: &str) -> Option<LockHashScheme> {
+    round_sources()
+        .into_iter()
+        .find(|source| source.name == name)
+        .map(|source| source.scheme)
+}
+
+// Recorded lock hash may be truncated as `0xe9e412caf497c6...a9b6d1ca51eb38d07d59`
+pub fn lock_hash_matches(recorded: &str, lock_hash: &H256) -> bool {
+    let actual = format!("{:#x}", lock_hash);
+    let recorded = recorded.trim().to_lowercase();
+    match recorded.find("...") {
+        Some(index) => {
+            let (prefix, suffix) = (&recorded[..index], &recorded[index + 3..]);
+            actual.starts_with(prefix) && actual.ends_with(suffix)
+        }
+        None => actual == recorded,
+    }
+}
+
+pub struct LockHashIssue {
+    pub source: &'static str,
+    pub line: u64,
+    pub address: String,
+    pub recorded: String,
+    pub reason: String,
+}
+
+pub fn verify_lock_hashes(source: &RoundSource) -> Vec<LockHashIssue> {
+    let mut issues = Vec::new();
+    let mut rdr = csv::Reader::from_reader(source.data.as_bytes());
+    for record in rdr.records() {
+        let record = record.unwrap();
+        let line = record.position().map(|position| position.line()).unwrap_or_default();
+        let address = record.get(source.address_column).unwrap_or_default();
+        let recorded = record.get(source.lock_hash_column).unwrap_or_default();
+        // Missing addresses are recovered and reported by the round loaders
+        if is_missing_address(address) {
+            continue;
+        }
+        let reason = match AddressParser.parse(address) {
+            Ok(hash) => {
+                let lock_hash = source.scheme.lock_hash(&hash);
+                if lock_hash_matches(recorded, &lock_hash) {
+                    continue;
+                }
+                format!("computed lock hash: {:#x}", lock_hash)
+            }
+            Err(err) => format!("invalid address: {}", err),
+        };
+        issues.push(LockHashIssue {
+            source: source.name,
+            line,
+            address: address.to_string(),
+            recorded: recorded.to_string(),
+            reason,
+        });
+    }
+    issues
+}
+
+pub fn print_lock_hash_report() -> usize {
+    let mut total_issues = 0;
+    println!("==== Lock hash verification");
+    for source in round_sources() {
+        let issues = verify_lock_hashes(&source);
+        println!("  {} ({:?}): {} issues", source.name, source.scheme, issues.len());
+        for issue in &issues {
+            println!(
+                "  > {}:{} address: {}, recorded: {}, {}",
+                issue.source, issue.line, issue.address, issue.recorded, issue.reason
+            );
+        }
+        total_issues += issues.len();
+    }
+    total_issues
+}
+
+pub struct RewardSource {
+    pub name: &'static str,
+    pub data: &'static str,
+    pub address_column: usize,
+    pub block_reward_column: usize,
+    pub reward_column: usize,
+}
+
+pub fn reward_sources() -> Vec<RewardSource> {
+    vec![
+        RewardSource {
+            name: "round2-miner",
+            data: crate::data::DATA_ROUND2_MINER,
+            address_column: 0,
+            block_reward_column: 1,
+            reward_column: 3,
+        },
+        RewardSource {
+            name: "round3-miner",
+            data: crate::data::DATA_ROUND3_MINER,
+            address_column: 0,
+            block_reward_column: 2,
+            reward_column: 3,
+        },
+        RewardSource {
+            name: "round4",
+            data: crate::data::DATA_ROUND4,
+            address_column: 0,
+            block_reward_column: 1,
+            reward_column: 3,
+        },
+        RewardSource {
+            name: "round5-stage1",
+            data: crate::data::DATA_ROUND5_STAGE1,
+            address_column: 0,
+            block_reward_column: 1,
+            reward_column: 3,
+        },
+        RewardSource {
+            name: "round5-stage2",
+            data: crate::data::DATA_ROUND5_STAGE2,
+            address_column: 0,
+            block_reward_column: 1,
+            reward_column: 3,
+        },
+    ]
+}
+
+// Block rewards are in shannons, round 5 has a `.00` suffix
+fn parse_block_reward(input: &str) -> Result<u128, String> {
+    let mut parts = input.trim().splitn(2, '.');
+    let integer = parts.next().unwrap();
+    if let Some(fraction) = parts.next() {
+        if fraction.chars().any(|c| c != '0') {
+            return Err(format!("fractional shannons: {}", input));
+        }
+    }
+    integer.parse::<u128>().map_err(|err| err.to_string())
+}
+
+// Stated rewards are floored, so the pool is the roundest number above
+// their sum which is less than one CKB per row away.
+pub fn infer_reward_pool(stated_total: u64, rows: usize) -> u64 {
+    let mut unit = 10u64.pow(12);
+    while unit > 1 {
+        let pool = (stated_total + unit - 1) / unit * unit;
+        if pool - stated_total < rows as u64 {
+            return pool;
+        }
+        unit /= 10;
+    }
+    stated_total
+}
+
+pub struct RewardIssue {
+    pub line: u64,
+    pub address: String,
+    pub block_reward: u128,
+    pub stated: u64,
+    pub expected: u64,
+}
+
+pub struct RewardCheck {
+    pub pool: u64,
+    pub stated_total: u64,
+    pub total_block_reward: u128,
+    pub issues: Vec<RewardIssue>,
+}
+
+// Expected reward (in CKB) is `floor(block_reward * pool / total_block_reward)`
+pub fn check_proportional_rewards(source: &RewardSource) -> Result<RewardCheck, String> {
+    let mut rows = Vec::new();
+    let mut rdr = csv::Reader::from_reader(source.data.as_bytes());
+    for record in rdr.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|position| position.line()).unwrap_or_default();
+        let address = record.get(source.address_column).unwrap_or_default().to_string();
+        let block_reward = record
+            .get(source.block_reward_column)
+            .ok_or_else(|| format!("{}:{} missing block reward", source.name, line))
+            .and_then(parse_block_reward)?;
+        let stated = record
+            .get(source.reward_column)
+            .ok_or_else(|| format!("{}:{} missing reward", source.name, line))?
+            .parse::<u64>()
+            .map_err(|err| format!("{}:{} {}", source.name, line, err))?;
+        rows.push((line, address, block_reward, stated));
+    }
+
+    let stated_total: u64 = rows.iter().map(|row| row.3).sum();
+    let total_block_reward: u128 = rows.iter().map(|row| row.2).sum();
+    let pool = infer_reward_pool(stated_total, rows.len());
+    let issues = rows
+        .into_iter()
+        .filter_map(|(line, address, block_reward, stated)| {
+            let expected = (block_reward * u128::from(pool) / total_block_reward) as u64;
+            if expected == stated {
+                None
+            } else {
+                Some(RewardIssue {
+                    line,
+                    address,
+                    block_reward,
+                    stated,
+                    expected,
+                })
+            }
+        })
+        .collect();
+    Ok(RewardCheck {
+        pool,
+        stated_total,
+        total_block_reward,
+        issues,
+    })
+}
+
+pub fn print_reward_report() -> usize {
+    let mut total_issues = 0;
+    println!("==== Proportional reward verification");
+    for source in reward_sources() {
+        let check = check_proportional_rewards(&source).unwrap();
+        println!(
+            "  {}: pool: {}, stated total: {}, total block reward: {}, issues: {}",
+            source.name,
+            check.pool,
+            check.stated_total,
+            check.total_block_reward,
+            check.issues.len()
+        );
+        for issue in &check.issues {
+            println!(
+                "  > {}:{} address: {}, block reward: {}, stated: {}, expected: {}",
+                source.name,
+                issue.line,
+                issue.address,
+                issue.block_reward,
+                issue.stated,
+                issue.expected
+            );
+        }
+        total_issues += check.issues.len();
+    }
+    total_issues
+}
+
+pub struct EpochLuckyCheck {
+    pub count: u64,
+    // Rows without address, recovered by lock hash or left to the testnet
+    // foundation remainder, see `AddressRecovery`
+    pub empty_rows: Vec<(u64, String)>,
+    pub paid_total: u64,
+    pub unpaid_total: u64,
+}
+
+// Every epoch from 1 to `epochs` is paid `epoch_reward` exactly once
+pub fn check_epoch_lucky(
+    name: &str,
+    data: &str,
+    epoch_reward: u64,
+    pool: u64,
+    epochs: u64,
+) -> Result<EpochLuckyCheck, String> {
+    if epoch_reward * epochs != pool {
+        return Err(format!(
+            "{}: {} epochs * {} != pool {}",
+            name, epochs, epoch_reward, pool
+        ));
+    }
+    let mut numbers = Vec::new();
+    let mut empty_rows = Vec::new();
+    let mut rdr = csv::Reader::from_reader(data.as_bytes());
+    for record in rdr.records() {
+        let record = record.map_err(|err| err.to_string())?;
+        let line = record.position().map(|position| position.line()).unwrap_or_default();
+        let number = record
+            .get(0)
+            .unwrap_or_default()
+            .trim()
+            .parse::<u64>()
+            .map_err(|err| format!("{}:{} invalid epoch number: {}", name, line, err))?;
+        if is_missing_address(record.get(1).unwrap_or_default()) {
+            empty_rows.push((number, record.get(2).unwrap_or_default().to_string()));
+        }
+        numbers.push(number);
+    }
+
+    let count = numbers.len() as u64;
+    if count != epochs {
+        return Err(format!("{}: {} rows, expected {} epochs", name, count, epochs));
+    }
+    numbers.sort();
+    for (index, number) in numbers.iter().enumerate() {
+        if *number != index as u64 + 1 {
+            return Err(format!(
+                "{}: epoch numbers are not unique and contiguous from 1, found {} at position {}",
+                name,
+                number,
+                index + 1
+            ));
+        }
+    }
+    let unpaid_total = empty_rows.len() as u64 * epoch_reward;
+    let paid_total = count * epoch_reward - unpaid_total;
+    Ok(EpochLuckyCheck {
+        count,
+        empty_rows,
+        paid_total,
+        unpaid_total,
+    })
+}
+
+pub fn print_epoch_lucky_report(recovery: &AddressRecovery) {
+    println!("==== Epoch lucky reward coverage");
+    for (name, data, epoch_reward, pool) in vec![
+        (
+            "round2-epoch",
+            crate::data::DATA_ROUND2_EPOCH,
+            crate::consts::EPOCH_REWARD2,
+            crate::consts::EPOCH_POOL2,
+        ),
+        (
+            "round3-epoch",
+            crate::data::DATA_ROUND3_EPOCH,
+            crate::consts::EPOCH_REWARD3,
+            crate::consts::EPOCH_POOL3,
+        ),
+    ] {
+        let epochs = crate::consts::EPOCH_LUCKY_EPOCHS;
+        let check = check_epoch_lucky(name, data, epoch_reward, pool, epochs).unwrap();
+        let scheme = round_scheme(name).unwrap();
+        let mut recovered_total = 0;
+        let mut lines = Vec::new();
+        for (number, lock_hash) in &check.empty_rows {
+            match recovery.lookup(lock_hash, &scheme) {
+                Ok((args, provenance)) => {
+                    recovered_total += epoch_reward;
+                    lines.push(format!(
+                        "  > epoch {} has no address, lock_hash: {}, recovered: {}, from: {}",
+                        number,
+                        lock_hash,
+                        Address::new_default(args).to_string(NetworkType::TestNet),
+                        provenance
+                    ));
+                }
+                Err(reason) => lines.push(format!(
+                    "  > epoch {} has no address, lock_hash: {}, {}, to testnet foundation",
+                    number, lock_hash, reason
+                )),
+            }
+        }
+        println!(
+            "  {}: epochs: {}, pool: {}, paid: {}, recovered: {}, to testnet foundation: {}",
+            name,
+            check.count,
+            pool,
+            check.paid_total,
+            recovered_total,
+            check.unpaid_total - recovered_total
+        );
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_lock_hash_schemes() {
+        let sources = round_sources();
+        for (source, address, recorded) in vec![
+            (
+                &sources[0],
+                "ckt1q9gry5zgvkprd780vqhlspq8gq5j0engkvwaqj350nxwq8",
+                "0xc9a17778b99b59067af358390da31f4de29ea669e93d19b199948ee4a1f51c40",
+            ),
+            (
+                &sources[2],
+                "ckt1qyq052ezneq9nn8qkp5tnv0z5uekmplpg5xq9qu7pc",
+                "0x958c3b2490661f43a6773c168007ca10cc7730c7d176afb62e0922facf83c9ea",
+            ),
+            (
+                &sources[4],
+                "ckt1qyqdmswal8qn2psmwc6u5508xh7zkq7wuvustsvyew",
+                "0x2285157e5ad963bc1ee81d799d31147d866c48b932305b28bd8a8af6460bb4b6",
+            ),
+            (
+                &sources[5],
+                "ckt1qyqdmeuqrsrnm7e5vnrmruzmsp4m9wacf6vsmcwugu",
+                "0xe9e412caf497c6...a9b6d1ca51eb38d07d59",
+            ),
+        ] {
+            let hash = AddressParser.parse(address).unwrap();
+            let lock_hash = source.scheme.lock_hash(&hash);
+            assert!(lock_hash_matches(recorded, &lock_hash), "{}", source.name);
+        }
+        // `NULL` rows of round2-miner are left to recovery
+        let issues = verify_lock_hashes(&sources[0]);
+        assert!(issues.iter().all(|issue| issue.address != "NULL"), "{}", issues.len());
+    }
+
+    #[test]
+    fn test_infer_reward_pool() {
+        assert_eq!(infer_reward_pool(1_999_935, 137), 2_000_000);
+        assert_eq!(infer_reward_pool(14_999_994, 16), 15_000_000);
+        assert_eq!(infer_reward_pool(3_000_000, 100), 3_000_000);
+        for source in reward_sources() {
+            let check = check_proportional_rewards(&source).unwrap();
+            assert!(check.issues.is_empty(), "{}", source.name);
+        }
+    }
+
+    #[test]
+    fn test_check_epoch_lucky() {
+        let data = "epoch_number,address,lock_hash\n2,ckt1a,0x02\n1,,0x01\n3,ckt1c,0x03\n";
+        let check = check_epoch_lucky("test", data, 100, 300, 3).unwrap();
+        assert_eq!(check.empty_rows, vec![(1, "0x01".to_string())]);
+        assert_eq!(check.paid_total, 200);
+        assert_eq!(check.unpaid_total, 100);
+
+        let duplicated = "epoch_number,address,lock_hash\n1,ckt1a,0x01\n1,ckt1b,0x02\n";
+        assert!(check_epoch_lucky("test", duplicated, 100, 200, 2).is_err());
+        assert!(check_epoch_lucky("test", data, 75, 300, 4).is_err());
+    }
+}
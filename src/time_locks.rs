@@ -0,0 +1,192 @@
+use ckb_types::core::EpochNumberWithFraction;
+
+use std::cmp::Ordering;
+
+use crate::{parse_lock_datetime, unlock_epoch};
+
+// Seconds per epoch assumed by `unlock_epoch`
+const EPOCH_DURATION: u64 = 14400;
+
+pub struct TimeLock {
+    pub source: String,
+    pub date: String,
+    // Seconds since the unix epoch
+    pub timestamp: i64,
+    pub epoch: EpochNumberWithFraction,
+}
+
+impl TimeLock {
+    // Unlock time estimated from the genesis timestamp, in seconds
+    pub fn estimated_timestamp(&self, genesis_timestamp: i64) -> i64 {
+        let seconds = self.epoch.number() * EPOCH_DURATION
+            + self.epoch.index() * EPOCH_DURATION / self.epoch.length();
+        genesis_timestamp + seconds as i64
+    }
+}
+
+fn compare_epochs(a: &EpochNumberWithFraction, b: &EpochNumberWithFraction) -> Ordering {
+    let value = |epoch: &EpochNumberWithFraction, other: &EpochNumberWithFraction| {
+        (u128::from(epoch.number()) * u128::from(epoch.length()) + u128::from(epoch.index()))
+            * u128::from(other.length())
+    };
+    value(a, b).cmp(&value(b, a))
+}
+
+// Time locked cells must unlock after genesis, at a non-zero epoch, in the
+// same order as their dates. The unlock estimated by 4 hours epochs should
+// stay within `tolerance` seconds from the date.
+#[derive(Default)]
+pub struct TimeLockCheck {
+    pub locks: Vec<TimeLock>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+pub fn check_time_locks(
+    locks: &[(String, String)],
+    genesis_timestamp_ms: u64,
+    last_epoch: u64,
+    tolerance: u64,
+) -> TimeLockCheck {
+    let genesis_timestamp = (genesis_timestamp_ms / 1000) as i64;
+    let mut result = TimeLockCheck::default();
+    for (source, date) in locks {
+        let timestamp = match parse_lock_datetime(date) {
+            Ok(datetime) => datetime.timestamp(),
+            Err(err) => {
+                result.errors.push(format!("{}: {}", source, err));
+                continue;
+            }
+        };
+        if timestamp <= genesis_timestamp {
+            result.errors.push(format!(
+                "{}: lock date {} is not after genesis timestamp {}",
+                source, date, genesis_timestamp_ms
+            ));
+        }
+        let epoch = match unlock_epoch(date, last_epoch) {
+            Ok(epoch) => epoch,
+            Err(err) => {
+                result.errors.push(format!("{}: {}", source, err));
+                continue;
+            }
+        };
+        if epoch.number() == 0 {
+            result.errors.push(format!("{}: lock date {} unlocks in epoch 0", source, date));
+        }
+        let lock = TimeLock {
+            source: source.clone(),
+            date: date.clone(),
+            timestamp,
+            epoch,
+        };
+        let drift = lock.estimated_timestamp(genesis_timestamp) - timestamp;
+        if drift.abs() as u64 > tolerance {
+            result.warnings.push(format!(
+                "{}: lock date {} is estimated to unlock {:+} seconds off at epoch {}",
+                source, date, drift, epoch
+            ));
+        }
+        result.locks.push(lock);
+    }
+
+    result.locks.sort_by_key(|lock| lock.timestamp);
+    for pair in result.locks.windows(2) {
+        let (earlier, later) = (&pair[0], &pair[1]);
+        if earlier.timestamp < later.timestamp
+            && compare_epochs(&earlier.epoch, &later.epoch) != Ordering::Less
+        {
+            result.errors.push(format!(
+                "{}: lock date {} at epoch {} does not unlock after {}: lock date {} at epoch {}",
+                later.source, later.date, later.epoch, earlier.source, earlier.date, earlier.epoch
+            ));
+        }
+    }
+    result
+}
+
+impl TimeLockCheck {
+    pub fn print_report(&self) {
+        println!(
+            "==== Time locks: {}, errors: {}, warnings: {}",
+            self.locks.len(),
+            self.errors.len(),
+            self.warnings.len()
+        );
+        let mut dates: Vec<(&String, &EpochNumberWithFraction)> =
+            self.locks.iter().map(|lock| (&lock.date, &lock.epoch)).collect();
+        dates.dedup();
+        for (date, epoch) in dates {
+            println!("  > {}: epoch {}", date, epoch);
+        }
+        for error in &self.errors {
+            println!("  > ERROR {}", error);
+        }
+        for warning in &self.warnings {
+            println!("  > WARN {}", warning);
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} invalid time locks", self.errors.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Mainnet epoch 0 starts at `SINCE_BEGIN` when the last testnet epoch is 89
+    const GENESIS_TIMESTAMP_MS: u64 = 1_573_884_000_000;
+
+    fn locks(dates: &[&str]) -> Vec<(String, String)> {
+        dates
+            .iter()
+            .enumerate()
+            .map(|(index, date)| (format!("row {}", index), date.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_time_locks() {
+        let result = check_time_locks(
+            &locks(&["2020-07-01", "2020-01-01T12:34:56+00:00", "2020-07-01"]),
+            GENESIS_TIMESTAMP_MS,
+            89,
+            3600,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        assert_eq!(result.locks[0].date, "2020-01-01T12:34:56+00:00");
+
+        let result = check_time_locks(
+            &locks(&["2019-11-16", "2020-07-01"]),
+            GENESIS_TIMESTAMP_MS,
+            89,
+            3600,
+        );
+        assert_eq!(result.errors.len(), 2, "{:?}", result.errors);
+        assert!(result.check().is_err());
+
+        // Genesis two days later than the last epoch implies
+        let result = check_time_locks(
+            &locks(&["2020-07-01"]),
+            GENESIS_TIMESTAMP_MS + 2 * 86_400_000,
+            89,
+            3600,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_unlock_epoch_underflow() {
+        let result = check_time_locks(&locks(&["2020-07-01"]), GENESIS_TIMESTAMP_MS, 10_000, 3600);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("underflows"), "{}", result.errors[0]);
+    }
+}